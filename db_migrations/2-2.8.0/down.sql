@@ -0,0 +1 @@
+DROP TABLE Event_backup;
\ No newline at end of file
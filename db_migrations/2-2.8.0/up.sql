@@ -0,0 +1,9 @@
+CREATE TABLE Event_backup
+(
+    uid TEXT PRIMARY KEY,
+    summary TEXT,
+    start_dt TEXT NOT NULL,
+    end_dt TEXT NOT NULL,
+    location TEXT,
+    description TEXT
+);
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE Event DROP COLUMN rrule;
+ALTER TABLE Event_backup DROP COLUMN rrule;
\ No newline at end of file
@@ -0,0 +1,2 @@
+ALTER TABLE Event ADD COLUMN rrule TEXT;
+ALTER TABLE Event_backup ADD COLUMN rrule TEXT;
\ No newline at end of file
@@ -0,0 +1 @@
+DROP INDEX Country_code;
\ No newline at end of file
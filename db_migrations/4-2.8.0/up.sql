@@ -0,0 +1,2 @@
+DELETE FROM Country WHERE id NOT IN (SELECT MAX(id) FROM Country GROUP BY code);
+CREATE UNIQUE INDEX Country_code ON Country (code);
\ No newline at end of file
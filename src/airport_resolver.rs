@@ -0,0 +1,283 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::config::*;
+
+
+/// # Summary
+/// Resolves IATA locations to ICAO locations, country, city, and airport name using the airport database. Results are cached, so repeated lookups of the same IATA location within one conversion only query the database once.
+pub struct AirportResolver
+{
+    db: r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, // airport database connection pool
+    icao_source_column: IcaoSourceColumn, // column to use as icao location
+    iata_to_icao_cache: std::sync::Mutex<std::collections::HashMap<String, Option<String>>>, // cached results of try_iata_to_icao, None if no icao location found
+    lookup_iata_cache: std::sync::Mutex<std::collections::HashMap<String, Option<IataLookupRow>>>, // cached results of lookup_iata, None if not exactly 1 entry found
+}
+
+impl AirportResolver
+{
+    /// # Summary
+    /// Creates a new airport resolver with empty cache.
+    ///
+    /// # Arguments
+    /// - `db`: airport database connection pool
+    /// - `icao_source_column`: column to use as icao location
+    ///
+    /// # Returns
+    /// - airport resolver
+    pub fn new(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, icao_source_column: IcaoSourceColumn) -> Self
+    {
+        return Self
+        {
+            db: db.clone(),
+            icao_source_column,
+            iata_to_icao_cache: std::sync::Mutex::new(std::collections::HashMap::new()),
+            lookup_iata_cache: std::sync::Mutex::new(std::collections::HashMap::new()),
+        };
+    }
+
+
+    /// # Summary
+    /// Takes an IATA location and tries to get the ICAO location, country, and airport name. The IATA location is trimmed and uppercased first. If no entry with that IATA code exists, tries matching the local code instead. If not exactly 1 entry could be found, returns None. If `icao_source_column` is gps_code, ICAO locations that are not 4 uppercase letters are treated as missing.
+    ///
+    /// # Arguments
+    /// - `iata`: IATA location
+    ///
+    /// # Returns
+    /// - ICAO location
+    /// - country name
+    /// - airport name
+    pub fn lookup_iata(&self, iata: &str) -> Option<IataLookupRow>
+    {
+        let iata: String = iata.trim().to_uppercase(); // normalize, source might contain lowercase or whitespace
+        let lookup_iata_query: String = format!("SELECT Airport.{} AS airport_gps_code, Airport.municipality AS airport_municipality, Country.name AS country_name, Airport.name AS airport_name FROM Airport JOIN Country ON Airport.iso_country = Country.code WHERE Airport.iata_code = ?;", self.icao_source_column.column_name()); // query string for iata lookup, column name is from whitelist so no injection possible
+        let lookup_local_code_query: String = format!("SELECT Airport.{} AS airport_gps_code, Airport.municipality AS airport_municipality, Country.name AS country_name, Airport.name AS airport_name FROM Airport JOIN Country ON Airport.iso_country = Country.code WHERE Airport.local_code = ?;", self.icao_source_column.column_name()); // query string for local code lookup as fallback
+        let map_row = |row: &rusqlite::Row| -> rusqlite::Result<IataLookupRow> // convert row to struct
+        {
+            return Ok(IataLookupRow
+            {
+                airport_name: row.get("airport_name")?,
+                airport_gps_code: self.filter_icao(row.get("airport_gps_code")?), // source sometimes contains non-standard values, treat them as missing
+                airport_municipality: row.get("airport_municipality")?,
+                country_name: row.get("country_name")?
+            });
+        };
+
+
+        if let Some(row) = self.lookup_iata_cache.lock().expect("Locking IATA lookup cache failed, because another thread panicked while holding it.").get(&iata) // if already looked up: use cached result
+        {
+            return row.clone();
+        }
+
+        let db_con = self.db.get().ok()?; // get connection, if failed do not cache
+        let query_result: rusqlite::Result<IataLookupRow> = match db_con.query_one(&lookup_iata_query, (&iata,), map_row) // execute query
+        {
+            Err(rusqlite::Error::QueryReturnedNoRows) => db_con.query_one(&lookup_local_code_query, (&iata,), map_row), // no iata match: try local code
+            result => result,
+        };
+        let row: Option<IataLookupRow> = match query_result
+        {
+            Ok(o) => Some(o),
+            Err(rusqlite::Error::QueryReturnedNoRows) | Err(rusqlite::Error::QueryReturnedMoreThanOneRow) => None, // not exactly 1 entry: cache None
+            Err(_) => {return None;}, // query failed: return None as if no icao location found, but do not cache
+        };
+
+        self.lookup_iata_cache.lock().expect("Locking IATA lookup cache failed, because another thread panicked while holding it.").insert(iata, row.clone());
+        return row;
+    }
+
+
+    /// # Summary
+    /// Takes an IATA location and tries to get the ICAO location. The IATA location is trimmed and uppercased first. If no entry with that IATA code exists, tries matching the local code instead. If no entry or no ICAO location could be found, returns input value unchanged. If `icao_source_column` is gps_code, ICAO locations that are not 4 uppercase letters are treated as missing.
+    ///
+    /// # Arguments
+    /// - `iata`: IATA location
+    ///
+    /// # Returns
+    /// - ICAO location or unchanged input value
+    pub fn try_iata_to_icao(&self, iata: &str) -> String
+    {
+        let iata_normalized: String = iata.trim().to_uppercase(); // normalize, source might contain lowercase or whitespace
+        let iata_to_icao_query: String = format!("SELECT {} AS icao FROM Airport WHERE iata_code = ?;", self.icao_source_column.column_name()); // query string for iata to icao lookup, column name is from whitelist so no injection possible
+        let local_code_to_icao_query: String = format!("SELECT {} AS icao FROM Airport WHERE local_code = ?;", self.icao_source_column.column_name()); // query string for local code to icao lookup as fallback
+
+
+        if let Some(icao) = self.iata_to_icao_cache.lock().expect("Locking IATA to ICAO cache failed, because another thread panicked while holding it.").get(&iata_normalized) // if already looked up: use cached result
+        {
+            return icao.clone().unwrap_or(iata.to_owned());
+        }
+
+        let db_con = match self.db.get() // get connection or fallback to return value unchanged without caching
+        {
+            Ok(o) => o,
+            Err(_) => {return iata.to_owned();},
+        };
+        let query_result: rusqlite::Result<Option<String>> = match db_con.query_one(&iata_to_icao_query, (&iata_normalized,), |row| {row.get::<&str, Option<String>>("icao")}) // execute query
+        {
+            Err(rusqlite::Error::QueryReturnedNoRows) => db_con.query_one(&local_code_to_icao_query, (&iata_normalized,), |row| {row.get::<&str, Option<String>>("icao")}), // no iata match: try local code
+            result => result,
+        };
+        let icao: Option<String> = match query_result
+        {
+            Ok(o) => self.filter_icao(o), // no icao location or non-standard value: cache None
+            Err(rusqlite::Error::QueryReturnedNoRows) | Err(rusqlite::Error::QueryReturnedMoreThanOneRow) => None, // not exactly 1 entry: cache None
+            Err(_) => {return iata.to_owned();}, // query failed: forward unchanged value, but do not cache
+        };
+
+        self.iata_to_icao_cache.lock().expect("Locking IATA to ICAO cache failed, because another thread panicked while holding it.").insert(iata_normalized, icao.clone());
+        return icao.unwrap_or(iata.to_owned()); // if no icao location found: forward unchanged value
+    }
+
+
+    /// # Summary
+    /// Treats non-standard values of the icao source column as missing. Only gps_code is checked to be 4 uppercase letters, because other columns like local_code legitimately contain shorter national codes.
+    ///
+    /// # Arguments
+    /// - `icao`: value of the icao source column
+    ///
+    /// # Returns
+    /// - icao location or None if missing or non-standard
+    fn filter_icao(&self, icao: Option<String>) -> Option<String>
+    {
+        match self.icao_source_column
+        {
+            IcaoSourceColumn::GpsCode => return icao.filter(|s| is_icao(s)),
+            IcaoSourceColumn::Ident | IcaoSourceColumn::LocalCode => return icao,
+        }
+    }
+}
+
+
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct IataLookupRow
+{
+    pub airport_name: String, // Airport.name
+    pub airport_gps_code: Option<String>, // Airport.gps_code or column configured in ICAO_SOURCE_COLUMN, icao location
+    pub airport_municipality: String, // Airport.municipality, city
+    pub country_name: String, // Country.name
+}
+
+
+/// # Summary
+/// Checks if a string looks like an ICAO location, meaning 4 uppercase letters. Airport data sometimes contains numbers or empty strings instead.
+///
+/// # Arguments
+/// - `s`: string to check
+///
+/// # Returns
+/// - true if ICAO location, false otherwise
+fn is_icao(s: &str) -> bool
+{
+    return s.len() == 4 && s.bytes().all(|b| b.is_ascii_uppercase());
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn lookups_are_cached()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+
+
+        assert_eq!(airport_resolver.lookup_iata("FRA").map(|row| row.airport_name), Some("Frankfurt am Main Airport".to_owned()));
+        assert_eq!(airport_resolver.try_iata_to_icao("FRA"), "EDDF");
+        assert_eq!(airport_resolver.lookup_iata("XXX"), None); // not found is cached as well
+        db.get().unwrap().execute("DELETE FROM Airport;", ()).unwrap(); // every further query would find nothing
+
+        assert_eq!(airport_resolver.lookup_iata("FRA").map(|row| row.airport_name), Some("Frankfurt am Main Airport".to_owned())); // answered from cache
+        assert_eq!(airport_resolver.try_iata_to_icao("FRA"), "EDDF");
+        assert_eq!(airport_resolver.lookup_iata("XXX"), None);
+        assert_eq!(AirportResolver::new(&db, IcaoSourceColumn::GpsCode).lookup_iata("FRA"), None); // new resolver has empty cache and queries again
+    }
+
+
+    #[test]
+    fn icao_source_column_selects_column()
+    {
+        let db = test_db();
+        seed_country(&db, 1, "DE", "Germany");
+        seed_airport(&db, 1, Some("FRA"), Some("EDDG"), "EDDI", Some("EDDL"), "Frankfurt am Main Airport", "Frankfurt am Main", "DE"); // every column distinct
+
+
+        for (icao_source_column, icao) in [(IcaoSourceColumn::GpsCode, "EDDG"), (IcaoSourceColumn::Ident, "EDDI"), (IcaoSourceColumn::LocalCode, "EDDL")]
+        {
+            let airport_resolver: AirportResolver = AirportResolver::new(&db, icao_source_column.clone());
+            assert_eq!(airport_resolver.try_iata_to_icao("FRA"), icao, "{icao_source_column:?}");
+            assert_eq!(airport_resolver.lookup_iata("FRA").and_then(|row| row.airport_gps_code), Some(icao.to_owned()), "{icao_source_column:?}");
+        }
+    }
+
+
+    #[test]
+    fn icao_source_column_deserializes_snake_case()
+    {
+        #[derive(serde::Deserialize)]
+        struct Wrapper
+        {
+            column: IcaoSourceColumn,
+        }
+
+
+        for (s, icao_source_column) in [("gps_code", IcaoSourceColumn::GpsCode), ("ident", IcaoSourceColumn::Ident), ("local_code", IcaoSourceColumn::LocalCode)]
+        {
+            assert_eq!(toml::from_str::<Wrapper>(format!("column = \"{s}\"").as_str()).unwrap().column, icao_source_column);
+            assert_eq!(icao_source_column.column_name(), s); // config value equals column name
+        }
+    }
+
+
+    #[test]
+    fn input_is_normalized_and_local_code_is_fallback()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        seed_airport(&db, 3, None, Some("EDXX"), "EDXX", Some("XYZ"), "Local Airfield", "Nowhere", "DE"); // no iata code, only local code
+        seed_airport(&db, 4, Some("ABC"), Some("12A"), "12A", None, "Odd Airfield", "Nowhere", "DE"); // non-standard icao location
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+
+
+        assert_eq!(airport_resolver.try_iata_to_icao(" fra "), "EDDF"); // trimmed and uppercased
+        assert_eq!(airport_resolver.lookup_iata("muc").map(|row| row.airport_municipality), Some("Munich".to_owned()));
+        assert_eq!(airport_resolver.try_iata_to_icao("XYZ"), "EDXX"); // found via local code
+        assert_eq!(airport_resolver.lookup_iata("XYZ").map(|row| row.airport_name), Some("Local Airfield".to_owned()));
+        assert_eq!(airport_resolver.try_iata_to_icao("ABC"), "ABC"); // non-standard icao location: input unchanged
+        assert_eq!(airport_resolver.lookup_iata("ABC").map(|row| row.airport_gps_code), Some(None)); // row found, icao location treated as missing
+        assert_eq!(airport_resolver.try_iata_to_icao("QQQ"), "QQQ"); // not found: input unchanged
+    }
+
+
+    #[test]
+    fn is_icao_needs_4_uppercase_letters()
+    {
+        assert!(is_icao("EDDF"));
+        assert!(!is_icao("eddf")); // lowercase
+        assert!(!is_icao("EdDF"));
+        assert!(!is_icao("FRA")); // 3 letters, iata length
+        assert!(!is_icao("EDDFX"));
+        assert!(!is_icao("ED12")); // alphanumeric
+        assert!(!is_icao("12A"));
+        assert!(!is_icao(""));
+        assert!(!is_icao("ÄDDF")); // non-ascii uppercase
+    }
+
+
+    #[test]
+    fn only_gps_code_is_checked_to_be_icao()
+    {
+        let db = test_db();
+        seed_country(&db, 1, "US", "United States");
+        seed_airport(&db, 1, Some("XYZ"), Some("12A"), "K12A", Some("12A"), "Small Airfield", "Nowhere", "US"); // local code is faa code
+
+
+        assert_eq!(AirportResolver::new(&db, IcaoSourceColumn::GpsCode).try_iata_to_icao("XYZ"), "XYZ"); // non-standard: input unchanged
+        assert_eq!(AirportResolver::new(&db, IcaoSourceColumn::LocalCode).try_iata_to_icao("XYZ"), "12A"); // configured explicitly: used as is
+        assert_eq!(AirportResolver::new(&db, IcaoSourceColumn::LocalCode).lookup_iata("XYZ").and_then(|row| row.airport_gps_code), Some("12A".to_owned()));
+        assert_eq!(AirportResolver::new(&db, IcaoSourceColumn::Ident).try_iata_to_icao("XYZ"), "K12A");
+    }
+}
\ No newline at end of file
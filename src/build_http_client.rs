@@ -0,0 +1,136 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::config::*;
+
+
+/// # Summary
+/// Creates the http client used for all downloads. Connecting has to be fast, but downloading the airport data can legitimately take a while, so both timeouts are set separately. Certificates are validated unless `ACCEPT_INVALID_CERTS` is set.
+///
+/// # Arguments
+/// - `config`: configuration, contains http timeouts, user agent, and certificate validation setting
+///
+/// # Returns
+/// - http client or error
+pub fn build_http_client(config: &Config) -> Result<reqwest::blocking::Client, reqwest::Error>
+{
+    let http_client: reqwest::blocking::Client; // http client
+    let http_client_settings: HttpClientSettings = HttpClientSettings::new(config); // settings with defaults applied
+
+
+    if http_client_settings.accept_invalid_certs
+    {
+        log::warn!("ACCEPT_INVALID_CERTS is enabled. TLS certificates are NOT validated for any request, including the authenticated input calendar download. Only use this if you know what you are doing.");
+    }
+
+    http_client = reqwest::blocking::Client::builder()
+        .danger_accept_invalid_certs(http_client_settings.accept_invalid_certs) // only accept invalid certificates if explicitly enabled, for example if ourairports.com's certificate is broken again
+        .user_agent(http_client_settings.user_agent)
+        .connect_timeout(Some(http_client_settings.connect_timeout))
+        .timeout(Some(http_client_settings.timeout))
+        .build()?;
+
+    return Ok(http_client);
+}
+
+
+/// # Summary
+/// Settings the http client is built with, resolved from the config. Kept separate from building, because a built client does not expose its settings.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct HttpClientSettings
+{
+    pub accept_invalid_certs: bool, // accept invalid tls certificates?
+    pub connect_timeout: std::time::Duration, // timeout for connecting to a server
+    pub timeout: std::time::Duration, // timeout for a whole request including download
+    pub user_agent: String, // user agent header value
+}
+
+impl HttpClientSettings
+{
+    /// # Summary
+    /// Resolves the http client settings from the config, applying defaults for unset settings.
+    ///
+    /// # Arguments
+    /// - `config`: configuration, contains http timeouts, user agent, and certificate validation setting
+    ///
+    /// # Returns
+    /// - http client settings
+    pub fn new(config: &Config) -> Self
+    {
+        return Self
+        {
+            accept_invalid_certs: config.ACCEPT_INVALID_CERTS.unwrap_or(false), // if ACCEPT_INVALID_CERTS unset default to false
+            connect_timeout: std::time::Duration::from_secs(config.HTTP_CONNECT_TIMEOUT.unwrap_or(10)), // if HTTP_CONNECT_TIMEOUT unset default to 10 s
+            timeout: std::time::Duration::from_secs(config.HTTP_READ_TIMEOUT.unwrap_or(60)), // whole request including download, if HTTP_READ_TIMEOUT unset default to 60 s
+            user_agent: config.HTTP_USER_AGENT.clone().unwrap_or(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_owned()), // some calendar providers block default user agent, if HTTP_USER_AGENT unset default to crate name and version
+        };
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn read_timeout_stops_hanging_download()
+    {
+        let listener: std::net::TcpListener = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        let url: String = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {let _stream = listener.accept(); std::thread::sleep(std::time::Duration::from_secs(10));}); // accept but never respond
+        let http_client: reqwest::blocking::Client = build_http_client(&Config {HTTP_CONNECT_TIMEOUT: Some(1), HTTP_READ_TIMEOUT: Some(1), ..Config::default()}).unwrap();
+        let t_start: std::time::Instant = std::time::Instant::now();
+
+
+        let result: Result<reqwest::blocking::Response, reqwest::Error> = http_client.get(url).send();
+
+        assert!(result.is_err_and(|e| e.is_timeout()));
+        assert!(t_start.elapsed() < std::time::Duration::from_secs(5)); // did not wait for server
+    }
+
+
+    #[test]
+    fn connect_timeout_stops_unreachable_server()
+    {
+        let http_client: reqwest::blocking::Client = build_http_client(&Config {HTTP_CONNECT_TIMEOUT: Some(1), HTTP_READ_TIMEOUT: Some(30), ..Config::default()}).unwrap();
+        let t_start: std::time::Instant = std::time::Instant::now();
+
+
+        let result: Result<reqwest::blocking::Response, reqwest::Error> = http_client.get("http://10.255.255.1:81").send(); // non-routable, connecting never succeeds
+
+        assert!(result.is_err_and(|e| e.is_connect())); // timed out or unreachable, depending on network
+        assert!(t_start.elapsed() < std::time::Duration::from_secs(5)); // stopped by connect timeout, not read timeout
+    }
+
+
+    #[test]
+    fn accept_invalid_certs_reaches_client()
+    {
+        assert!(!HttpClientSettings::new(&Config::default()).accept_invalid_certs); // unset: validating
+        assert!(!HttpClientSettings::new(&Config {ACCEPT_INVALID_CERTS: Some(false), ..Config::default()}).accept_invalid_certs);
+        assert!(HttpClientSettings::new(&Config {ACCEPT_INVALID_CERTS: Some(true), ..Config::default()}).accept_invalid_certs);
+        assert!(build_http_client(&Config {ACCEPT_INVALID_CERTS: Some(true), ..Config::default()}).is_ok());
+    }
+
+
+    #[test]
+    fn settings_defaults()
+    {
+        assert_eq!(HttpClientSettings::new(&Config::default()), HttpClientSettings {accept_invalid_certs: false, connect_timeout: std::time::Duration::from_secs(10), timeout: std::time::Duration::from_secs(60), user_agent: concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_owned()});
+    }
+
+
+    #[test]
+    fn user_agent_default_and_custom()
+    {
+        let (url, requests) = serve(vec![(None, Vec::new()), (None, Vec::new())]);
+
+
+        build_http_client(&Config::default()).unwrap().get(url.as_str()).send().unwrap();
+        build_http_client(&Config {HTTP_USER_AGENT: Some("Mozilla/5.0".to_owned()), ..Config::default()}).unwrap().get(url.as_str()).send().unwrap();
+
+        assert_eq!(request_header(&requests.recv().unwrap(), "User-Agent"), Some(concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION")).to_owned()));
+        assert_eq!(request_header(&requests.recv().unwrap(), "User-Agent"), Some("Mozilla/5.0".to_owned()));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,101 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::error::*;
+
+
+/// # Summary
+/// Counts the rows of the database tables "Airport" and "Country" to verify they are populated before relying on lookups.
+///
+/// # Arguments
+/// - `db`: database connection pool
+///
+/// # Returns
+/// - row counts or error
+pub fn check_db(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> Result<DbCheck, CheckDbError>
+{
+    const AIRPORT_QUERY: &str = "SELECT COUNT(*), COUNT(iata_code) FROM Airport;"; // query string for Airport table, COUNT(column) skips null
+    const COUNTRY_QUERY: &str = "SELECT COUNT(*) FROM Country;"; // query string for Country table
+    let db_check: DbCheck; // row counts
+
+
+    let db_con = db.get()?; // get connection
+    let (airports, airports_with_iata): (usize, usize) = db_con.query_one(AIRPORT_QUERY, (), |row| Ok((row.get(0)?, row.get(1)?)))?;
+    let countries: usize = db_con.query_one(COUNTRY_QUERY, (), |row| row.get(0))?;
+    db_check = DbCheck {airports, airports_with_iata, countries};
+
+    return Ok(db_check);
+}
+
+
+/// # Summary
+/// Row counts of the airport and country data.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DbCheck
+{
+    pub airports: usize, // rows in Airport
+    pub airports_with_iata: usize, // rows in Airport with iata_code, only these can be looked up
+    pub countries: usize, // rows in Country
+}
+
+impl DbCheck
+{
+    /// # Summary
+    /// Determines if the row counts are plausible. The full data set has about 80.000 airports, about 9.000 of them with IATA code, and about 250 countries, so counts far below indicate an incomplete or failed update.
+    ///
+    /// # Returns
+    /// - true if plausible, false otherwise
+    pub fn is_plausible(&self) -> bool
+    {
+        const AIRPORTS_MIN: usize = 40000; // minimum number of airports
+        const AIRPORTS_WITH_IATA_MIN: usize = 5000; // minimum number of airports with iata code
+        const COUNTRIES_MIN: usize = 200; // minimum number of countries
+
+
+        return AIRPORTS_MIN <= self.airports && AIRPORTS_WITH_IATA_MIN <= self.airports_with_iata && COUNTRIES_MIN <= self.countries;
+    }
+}
+
+impl std::fmt::Display for DbCheck
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{} airports ({} with IATA code), {} countries", self.airports, self.airports_with_iata, self.countries)
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn counts_rows()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        seed_airport(&db, 3, None, None, "DE-0003", None, "Heliport", "Nowhere", "DE"); // no iata code
+
+
+        let db_check: DbCheck = check_db(&db).unwrap();
+
+        assert_eq!(db_check, DbCheck {airports: 3, airports_with_iata: 2, countries: 1});
+        assert_eq!(db_check.to_string(), "3 airports (2 with IATA code), 1 countries");
+        assert!(!db_check.is_plausible()); // far below full data set
+    }
+
+
+    #[test]
+    fn plausibility_thresholds()
+    {
+        let db_check: DbCheck = DbCheck {airports: 40000, airports_with_iata: 5000, countries: 200}; // exactly at minimum
+
+
+        assert!(db_check.is_plausible());
+        assert!(!DbCheck {airports: 39999, ..db_check.clone()}.is_plausible());
+        assert!(!DbCheck {airports_with_iata: 4999, ..db_check.clone()}.is_plausible());
+        assert!(!DbCheck {countries: 199, ..db_check.clone()}.is_plausible());
+        assert!(!check_db(&test_db()).unwrap().is_plausible()); // empty database
+    }
+}
\ No newline at end of file
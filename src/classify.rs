@@ -0,0 +1,50 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::event_type::*;
+use crate::event_type_patterns::*;
+
+
+/// # Summary
+/// Determines the event type of a summary for the "classify" command, to test event type patterns without running the converter. If the summary is unknown, adds the closest pattern to help crafting new patterns.
+///
+/// # Arguments
+/// - `summary`: event summary to classify
+/// - `event_type_patterns`: compiled patterns to determine event types
+///
+/// # Returns
+/// - event type with its captured fields and diagnosis if unknown, ready to print
+pub fn classify(summary: String, event_type_patterns: &EventTypePatterns) -> String
+{
+    let (event_type, unknown_diagnosis) = EventType::determine_event_type_verbose(summary, event_type_patterns);
+    let mut s: String = format!("{event_type:?}"); // output
+
+
+    if let Some(unknown_diagnosis) = unknown_diagnosis {s.push_str(format!("\n{unknown_diagnosis}").as_str());} // unknown: show closest pattern
+
+    return s;
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn flight_is_classified_with_captures()
+    {
+        assert_eq!(classify("LH 100: FRA-MUC".to_owned(), &patterns_default()), r#"Flight { flight_iata: "LH100", departure_iata: "FRA", destination_iata: "MUC" }"#);
+    }
+
+
+    #[test]
+    fn unknown_is_classified_with_diagnosis()
+    {
+        let output: String = classify("SOMETHING NEW".to_owned(), &patterns_default());
+
+
+        assert!(output.starts_with("Unknown\nTried patterns: "));
+        assert!(output.contains("\nClosest pattern: "));
+    }
+}
\ No newline at end of file
@@ -0,0 +1,153 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::error::*;
+
+
+/// # Summary
+/// Action to execute, determined by the command line arguments.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum CliAction
+{
+    CheckDb, // report airport and country row counts and exit
+    Classify(String), // determine event type of summary, print it, and exit
+    Help, // print usage summary and exit
+    ResetDb, // delete all events from database and exit
+    Run, // run converter, default without arguments
+    Version, // print version and exit
+}
+
+impl CliAction
+{
+    /// # Summary
+    /// Determines the action to execute from the command line arguments.
+    ///
+    /// # Arguments
+    /// - `args`: command line arguments without program name
+    ///
+    /// # Returns
+    /// - action to execute or error if arguments are invalid
+    pub fn parse(args: &[String]) -> Result<Self, ParseCliActionError>
+    {
+        match args.iter().map(|s| s.as_str()).collect::<Vec<&str>>().as_slice()
+        {
+            [] => return Ok(Self::Run),
+            ["check-db"] => return Ok(Self::CheckDb),
+            ["classify", summary @ ..] if !summary.is_empty() => return Ok(Self::Classify(summary.join(" "))), // join in case summary was not quoted
+            ["-h"] | ["--help"] => return Ok(Self::Help),
+            ["reset-db"] => return Ok(Self::ResetDb),
+            ["-V"] | ["--version"] => return Ok(Self::Version),
+            _ => return Err(ParseCliActionError::InvalidArguments(args.join(" "))),
+        }
+    }
+
+
+    /// # Summary
+    /// Returns the usage summary including config path and supported settings.
+    ///
+    /// # Returns
+    /// - usage summary
+    pub fn help() -> String
+    {
+        return format!
+        (
+"{} {}
+Regularly downloads the DLH myTime duty plan, converts it, and saves the output calendar.
+
+Usage:
+    {0}                         run converter
+    {0} check-db                report airport and country row counts, fail if implausibly low, and exit
+    {0} classify <summary>      determine event type of summary, print it, and exit
+    {0} reset-db                delete all events from database so next run pulls whole calendar again, airports and countries are kept
+    {0} --help                  print this help and exit
+    {0} --version               print version and exit
+
+Config is loaded from environment variables and from the TOML file \"./config/.env\", environment variables take precedence.
+Set CONFIG_FILEPATHS to a comma separated list of TOML files to load instead, later files override earlier ones. If no config is found, a default config is only created at the first file path.
+Event type patterns can be overridden in the optional TOML file \"./config/patterns.toml\" mapping event type names to regex strings, for example Flight = '^(?P<flight_iata>...): ...$'.
+
+Settings:
+    ACCEPT_INVALID_CERTS        disable tls certificate validation for all requests, dangerous, default false
+    AIRPORT_REFRESH_INTERVAL    interval in seconds to download airport and country data again in background, default only at startup
+    ALARM_ACTION                how alarms notify, \"display\", \"audio\", or \"email\", default \"display\"
+    ALARM_EMAIL                 email address to send alarms to, required if ALARM_ACTION is \"email\"
+    ARCHIVE_END_ABSOLUTE        when archive ends as rfc3339 datetime, overrides ARCHIVE_END_RELATIVE, default none
+    ARCHIVE_END_RELATIVE        when relative to now archive ends, minus is past, plus is future
+    ARCHIVE_MAX_AGE             how long after their end events are kept in database, default forever
+    DB_BUSY_TIMEOUT             how long to wait for a locked database in milliseconds, default 5000
+    DEBUG                       debug mode, default false
+    DISABLE_ALARMS              omit all alarms, default false
+    EXCLUDED_EVENT_TYPES        event types to omit from output calendar, for example [\"Off\", \"Sickness\"], default none
+    FLIGHT_SEPARATOR            separator between departure and destination in flight and deadhead summaries, default \"✈\"
+    GROUND_CATEGORY_MAP         map ground event categories to shorter versions, for example {{\"Office Day\" = \"Office\"}}, extends built-in mapping
+    GROUND_DEFAULT_CATEGORY     category prefix for ground events without category, default none
+    GROUP_DUTIES                add event spanning each duty from briefing to last flight, default false
+    GROUP_DUTIES_MAX_GAP        maximum gap between briefing and flights to still be considered the same duty, default 3 h
+    HTTP_CONNECT_TIMEOUT        timeout for connecting to a server in seconds, default 10
+    HTTP_READ_TIMEOUT           timeout for a whole request including download in seconds, default 60
+    HTTP_USER_AGENT             user agent for all requests, default \"{0}/{1}\"
+    ICAO_SOURCE_COLUMN          airport database column to use as icao location, \"gps_code\", \"ident\", or \"local_code\", default \"gps_code\"
+    INPUT_CALENDAR_AUTH         authentication for downloading original calendar, {{basic = {{user, pass}}}} or {{bearer = token}}
+    INPUT_CALENDAR_URL          original calendar url to read from
+    INPUT_TIMEZONE              iana timezone of floating datetimes in original calendar, for example \"Europe/Berlin\", default UTC
+    ITERATION_TIMEOUT           maximum duration of 1 iteration in seconds, if exceeded skip iterations until it finished, default none
+    KEEP_ORIGINAL_SUMMARY       add untransformed summary as X-ORIGINAL-SUMMARY property, default false
+    LOCATION_FORMAT             location format with placeholders {{icao}}, {{iata}}, {{country}}, {{city}}, {{name}}, default depends on event type
+    LOG_RETENTION_DAYS          delete log files older than this many days on startup, default forever
+    MASK_PRIVATE_EVENTS         replace summaries of holiday, off, and sickness with PRIVATE_EVENT_LABEL, default false
+    MIN_ACTIVE_EVENTS_RATIO     minimum ratio of active events after update compared to before, otherwise restore backup, default none (never restore)
+    MIN_EVENT_DURATION          extend events shorter than this to it by moving their end, all-day events unaffected, default none
+    MIN_PARSE_SUCCESS_RATIO     minimum ratio of successfully parsed airport and country csv rows to update database, default 0.9
+    OUTPUT_CALENDAR_FILEPATH    file path or list of file paths to write calendar to
+    OUTPUT_CALENDAR_METHOD      iCalendar METHOD of output calendar, default none
+    OUTPUT_MODE                 what output calendar contains, \"events\" or \"freebusy\" to only share availability, default \"events\"
+    PRIVATE_EVENT_LABEL         summary of masked private events, default \"Unavailable\"
+    SIMULATOR_ALARM             additional earlier alarm for simulator sessions relative to start, minus is before, default -2 h
+    SLEEP_INTERVAL              sleep interval between calendar updates in seconds
+    SLEEP_JITTER                maximum random addition to sleep interval in seconds, default 0
+    TAG_ISO_WEEK                add iso week and weekday to description, for example \"2024-W32 Thu\", default false
+    TAG_REDEYE                  prefix flights departing between 22:00 and 05:00 UTC with \"🌙\", default false
+    TIME_PREFIX_MODE            leading local time of briefing and pickup summaries, \"keep\", \"strip\", or \"12h\", default \"strip\"
+    WARN_ON_OVERLAP             log warning if active events overlap, default false",
+            env!("CARGO_PKG_NAME"),
+            env!("CARGO_PKG_VERSION"),
+        );
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+
+    /// # Summary
+    /// Parses command line arguments given as string slices.
+    fn parse(args: &[&str]) -> Result<CliAction, ParseCliActionError>
+    {
+        return CliAction::parse(&args.iter().map(|s| s.to_string()).collect::<Vec<String>>());
+    }
+
+
+    #[test]
+    fn arguments_are_parsed()
+    {
+        assert_eq!(parse(&[]).unwrap(), CliAction::Run);
+        assert_eq!(parse(&["check-db"]).unwrap(), CliAction::CheckDb);
+        assert_eq!(parse(&["classify", "LH 100: FRA-MUC"]).unwrap(), CliAction::Classify("LH 100: FRA-MUC".to_owned()));
+        assert_eq!(parse(&["classify", "LH", "100:", "FRA-MUC"]).unwrap(), CliAction::Classify("LH 100: FRA-MUC".to_owned())); // unquoted summary is joined
+        assert_eq!(parse(&["-h"]).unwrap(), CliAction::Help);
+        assert_eq!(parse(&["--help"]).unwrap(), CliAction::Help);
+        assert_eq!(parse(&["reset-db"]).unwrap(), CliAction::ResetDb);
+        assert_eq!(parse(&["-V"]).unwrap(), CliAction::Version);
+        assert_eq!(parse(&["--version"]).unwrap(), CliAction::Version);
+    }
+
+
+    #[test]
+    fn invalid_arguments_are_rejected()
+    {
+        assert!(parse(&["classify"]).is_err()); // summary missing
+        assert!(parse(&["--version", "--help"]).is_err());
+        assert!(parse(&["run"]).is_err());
+    }
+}
\ No newline at end of file
@@ -3,15 +3,53 @@
 
 /// # Summary
 /// Collection of settings making up the configuration of the application.
-#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
 #[allow(non_snake_case)]
 pub struct Config
 {
+    pub ACCEPT_INVALID_CERTS: Option<bool>, // disable tls certificate validation for all requests? dangerous
+    pub AIRPORT_REFRESH_INTERVAL: Option<u64>, // interval in seconds to download airport and country data again in background, for long running instances
+    pub ALARM_ACTION: Option<AlarmAction>, // how alarms notify, "display", "audio", or "email"
+    pub ALARM_EMAIL: Option<String>, // email address to send alarms to, required if ALARM_ACTION is "email"
+    pub ARCHIVE_END_ABSOLUTE: Option<chrono::DateTime<chrono::Utc>>, // when archive ends, overrides ARCHIVE_END_RELATIVE, for reproducible reprocessing
     pub ARCHIVE_END_RELATIVE: chrono::Duration, // when relative to now archive ends, minus is past, plus is future
+    pub ARCHIVE_MAX_AGE: Option<chrono::Duration>, // how long after their end events are kept in database
+    pub DB_BUSY_TIMEOUT: Option<u64>, // how long to wait for a locked database in milliseconds, for example if a web server reads it concurrently
     pub DEBUG: Option<bool>, // debug mode?
+    pub DISABLE_ALARMS: Option<bool>, // omit all alarms, for devices with their own notification scheme
+    pub EXCLUDED_EVENT_TYPES: Option<Vec<String>>, // event types to omit from output calendar, for example ["Off", "Sickness"]
+    pub FLIGHT_SEPARATOR: Option<String>, // separator between departure and destination in flight and deadhead summaries
+    pub GROUND_CATEGORY_MAP: Option<std::collections::HashMap<String, String>>, // map ground event categories to shorter versions, extends and overrides built-in mapping
+    pub GROUND_DEFAULT_CATEGORY: Option<String>, // category prefix for ground events without category
+    pub GROUP_DUTIES: Option<bool>, // add event spanning each duty from briefing to last flight?
+    pub GROUP_DUTIES_MAX_GAP: Option<chrono::Duration>, // maximum gap between briefing and flights to still be considered the same duty
+    pub HTTP_CONNECT_TIMEOUT: Option<u64>, // timeout for connecting to a server in seconds
+    pub HTTP_READ_TIMEOUT: Option<u64>, // timeout for a whole request including download in seconds
+    pub HTTP_USER_AGENT: Option<String>, // user agent for all requests
+    pub ICAO_SOURCE_COLUMN: Option<IcaoSourceColumn>, // airport database column to use as icao location, "gps_code", "ident", or "local_code"
+    pub INPUT_CALENDAR_AUTH: Option<InputCalendarAuth>, // authentication for downloading original calendar
     pub INPUT_CALENDAR_URL: String, // original calendar url to read from
-    pub OUTPUT_CALENDAR_FILEPATH: String, // file path to write calendar to
+    pub INPUT_TIMEZONE: Option<chrono_tz::Tz>, // iana timezone of floating datetimes in original calendar
+    pub ITERATION_TIMEOUT: Option<u64>, // maximum duration of 1 iteration in seconds, if exceeded stop waiting for it, following iterations are skipped until it finished
+    pub KEEP_ORIGINAL_SUMMARY: Option<bool>, // add untransformed summary as X-ORIGINAL-SUMMARY property?
+    pub LOCATION_FORMAT: Option<String>, // location format with placeholders {icao}, {iata}, {country}, {city}, {name}
+    pub LOG_RETENTION_DAYS: Option<u64>, // delete log files older than this many days on startup
+    pub MASK_PRIVATE_EVENTS: Option<bool>, // replace summaries of holiday, off, and sickness with PRIVATE_EVENT_LABEL, for shared calendars
+    pub MIN_ACTIVE_EVENTS_RATIO: Option<f64>, // minimum ratio of active events after update compared to before, otherwise restore backup, unset never restores
+    pub MIN_EVENT_DURATION: Option<chrono::Duration>, // events shorter than this are extended to it by moving their end, all-day events are unaffected
+    pub MIN_PARSE_SUCCESS_RATIO: Option<f64>, // minimum ratio of successfully parsed airport and country csv rows to update database
+    #[serde(deserialize_with = "string_or_vec")]
+    pub OUTPUT_CALENDAR_FILEPATH: Vec<String>, // file paths to write calendar to, single path or list
+    pub OUTPUT_CALENDAR_METHOD: Option<String>, // iCalendar METHOD of output calendar, for example "PUBLISH"
+    pub OUTPUT_MODE: Option<OutputMode>, // what output calendar contains, "events" or "freebusy"
+    pub PRIVATE_EVENT_LABEL: Option<String>, // summary of masked private events
+    pub SIMULATOR_ALARM: Option<chrono::Duration>, // additional earlier alarm for simulator sessions relative to start, minus is before
     pub SLEEP_INTERVAL: u64, // sleep interval between calendar updates
+    pub SLEEP_JITTER: Option<u64>, // maximum random addition to sleep interval
+    pub TAG_ISO_WEEK: Option<bool>, // add iso week and weekday to description, for example "2024-W32 Thu"?
+    pub TAG_REDEYE: Option<bool>, // prefix flights departing between 22:00 and 05:00 utc with "🌙"?
+    pub TIME_PREFIX_MODE: Option<TimePrefixMode>, // what to do with leading local time of briefing and pickup summaries, "keep", "strip", or "12h"
+    pub WARN_ON_OVERLAP: Option<bool>, // log warning if active events overlap?
 }
 
 impl Default for Config
@@ -20,11 +58,198 @@ impl Default for Config
     {
         Self
         {
+            ACCEPT_INVALID_CERTS: None, // no entry in default config, defaults to false
+            AIRPORT_REFRESH_INTERVAL: None, // no entry in default config, defaults to only downloading at startup
+            ALARM_ACTION: None, // no entry in default config, defaults to "display"
+            ALARM_EMAIL: None, // no entry in default config, defaults to no email address
+            ARCHIVE_END_ABSOLUTE: None, // no entry in default config, defaults to using ARCHIVE_END_RELATIVE
             ARCHIVE_END_RELATIVE: chrono::Duration::weeks(-1), // default archive end is one week ago, everything at that datetime or older is archived
+            ARCHIVE_MAX_AGE: None, // no entry in default config, defaults to keeping events forever
+            DB_BUSY_TIMEOUT: None, // no entry in default config, defaults to 5000 ms
             DEBUG: None, // no entry in default config, defaults to false
+            DISABLE_ALARMS: None, // no entry in default config, defaults to false
+            EXCLUDED_EVENT_TYPES: None, // no entry in default config, defaults to excluding nothing
+            FLIGHT_SEPARATOR: None, // no entry in default config, defaults to "✈"
+            GROUND_CATEGORY_MAP: None, // no entry in default config, defaults to built-in mapping only
+            GROUND_DEFAULT_CATEGORY: None, // no entry in default config, defaults to no prefix
+            GROUP_DUTIES: None, // no entry in default config, defaults to false
+            GROUP_DUTIES_MAX_GAP: None, // no entry in default config, defaults to 3 h
+            HTTP_CONNECT_TIMEOUT: None, // no entry in default config, defaults to 10 s
+            HTTP_READ_TIMEOUT: None, // no entry in default config, defaults to 60 s
+            HTTP_USER_AGENT: None, // no entry in default config, defaults to "{crate name}/{crate version}"
+            ICAO_SOURCE_COLUMN: None, // no entry in default config, defaults to "gps_code"
+            INPUT_CALENDAR_AUTH: None, // no entry in default config, defaults to no authentication
             INPUT_CALENDAR_URL: "".to_owned(), // default calendar url
-            OUTPUT_CALENDAR_FILEPATH: "./calendar/duty_plan.ics".to_owned(), // default calendar file path
+            INPUT_TIMEZONE: None, // no entry in default config, defaults to utc
+            ITERATION_TIMEOUT: None, // no entry in default config, defaults to waiting forever
+            KEEP_ORIGINAL_SUMMARY: None, // no entry in default config, defaults to false
+            LOCATION_FORMAT: None, // no entry in default config, defaults to "{icao}: {country}, {name}" for briefings and flights and "{country}, {city}" otherwise
+            LOG_RETENTION_DAYS: None, // no entry in default config, defaults to keeping log files forever
+            MASK_PRIVATE_EVENTS: None, // no entry in default config, defaults to false
+            MIN_ACTIVE_EVENTS_RATIO: None, // no entry in default config, defaults to never restoring backup
+            MIN_EVENT_DURATION: None, // no entry in default config, defaults to not extending events
+            MIN_PARSE_SUCCESS_RATIO: None, // no entry in default config, defaults to 0.9
+            OUTPUT_CALENDAR_FILEPATH: vec!["./calendar/duty_plan.ics".to_owned()], // default calendar file path
+            OUTPUT_CALENDAR_METHOD: None, // no entry in default config, defaults to no METHOD
+            OUTPUT_MODE: None, // no entry in default config, defaults to "events"
+            PRIVATE_EVENT_LABEL: None, // no entry in default config, defaults to "Unavailable"
+            SIMULATOR_ALARM: None, // no entry in default config, defaults to -2 h
             SLEEP_INTERVAL: 500, // default sleep interval
+            SLEEP_JITTER: None, // no entry in default config, defaults to 0
+            TAG_ISO_WEEK: None, // no entry in default config, defaults to false
+            TAG_REDEYE: None, // no entry in default config, defaults to false
+            TIME_PREFIX_MODE: None, // no entry in default config, defaults to "strip"
+            WARN_ON_OVERLAP: None, // no entry in default config, defaults to false
         }
     }
+}
+
+
+/// # Summary
+/// Action of the alarms added to events, determines how the calendar application notifies.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AlarmAction
+{
+    Audio, // play sound
+    Display, // show notification
+    Email, // send email to ALARM_EMAIL
+}
+
+
+/// # Summary
+/// Column of the airport database to use as ICAO location. Only these columns are allowed, so the column name can safely be inserted into queries.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IcaoSourceColumn
+{
+    GpsCode, // Airport.gps_code
+    Ident, // Airport.ident, often equals icao location
+    LocalCode, // Airport.local_code
+}
+
+impl IcaoSourceColumn
+{
+    /// # Summary
+    /// Returns the column name in table "Airport".
+    ///
+    /// # Returns
+    /// - column name
+    pub fn column_name(&self) -> &'static str
+    {
+        match self
+        {
+            Self::GpsCode => return "gps_code",
+            Self::Ident => return "ident",
+            Self::LocalCode => return "local_code",
+        }
+    }
+}
+
+/// # Summary
+/// Authentication method for downloading the input calendar. Either `{basic = {user = "...", pass = "..."}}` or `{bearer = "..."}`.
+#[derive(Clone, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputCalendarAuth
+{
+    Basic {user: String, pass: String}, // http basic auth
+    Bearer(String), // bearer token
+}
+
+impl std::fmt::Debug for InputCalendarAuth
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        match self // never print credentials, config is logged in debug mode
+        {
+            Self::Basic {user: _, pass: _} => write!(f, "Basic {{user: <redacted>, pass: <redacted>}}"),
+            Self::Bearer(_) => write!(f, "Bearer(<redacted>)"),
+        }
+    }
+}
+
+
+/// # Summary
+/// What the output calendar contains.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputMode
+{
+    Events, // transformed events
+    FreeBusy, // only VFREEBUSY, duties busy and off days free, to share availability without details
+}
+
+
+/// # Summary
+/// What to do with the leading local time, for example "06:15 LT", of briefing and pickup summaries.
+#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimePrefixMode
+{
+    Keep, // keep as is, for example "06:15 LT Briefing"
+    Strip, // remove, for example "Briefing"
+    #[serde(rename = "12h")]
+    TwelveHour, // reformat to 12 h, for example "6:15 AM LT Briefing"
+}
+
+
+/// # Summary
+/// Parses either a single string or a list of strings to a list of strings, so settings can be extended to multiple values without breaking existing configs.
+///
+/// # Arguments
+/// - `deserializer`: serde deserializer
+///
+/// # Returns
+/// - list of strings or serde::Error
+fn string_or_vec<'de, D>(deserializer: D) -> Result<Vec<String>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(serde::Deserialize)]
+    #[serde(untagged)]
+    enum StringOrVec
+    {
+        String(String), // single value
+        Vec(Vec<String>), // list of values
+    }
+
+
+    match serde::Deserialize::deserialize(deserializer)?
+    {
+        StringOrVec::String(s) => return Ok(vec![s]),
+        StringOrVec::Vec(v) => return Ok(v),
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+
+    #[test]
+    fn input_calendar_auth_debug_is_redacted()
+    {
+        let basic: String = format!("{:?}", InputCalendarAuth::Basic {user: "secret_user".to_owned(), pass: "secret_pass".to_owned()});
+        let bearer: String = format!("{:?}", InputCalendarAuth::Bearer("secret_token".to_owned()));
+        let config: String = format!("{:?}", Config {INPUT_CALENDAR_AUTH: Some(InputCalendarAuth::Bearer("secret_token".to_owned())), ..Config::default()}); // config is logged in debug mode
+
+
+        assert!(!basic.contains("secret"));
+        assert!(!bearer.contains("secret"));
+        assert!(!config.contains("secret"));
+        assert!(basic.contains("<redacted>"));
+    }
+
+
+    #[test]
+    fn output_calendar_filepath_accepts_string_or_list()
+    {
+        let config_default: String = toml::to_string(&Config::default()).unwrap().lines().filter(|line| !line.starts_with("OUTPUT_CALENDAR_FILEPATH")).collect::<Vec<&str>>().join("\n"); // complete config except output, top-level keys come before tables
+
+
+        assert_eq!(toml::from_str::<Config>(format!("OUTPUT_CALENDAR_FILEPATH = \"a.ics\"\n{config_default}").as_str()).unwrap().OUTPUT_CALENDAR_FILEPATH, vec!["a.ics".to_owned()]);
+        assert_eq!(toml::from_str::<Config>(format!("OUTPUT_CALENDAR_FILEPATH = [\"a.ics\", \"b.ics\"]\n{config_default}").as_str()).unwrap().OUTPUT_CALENDAR_FILEPATH, vec!["a.ics".to_owned(), "b.ics".to_owned()]);
+    }
 }
\ No newline at end of file
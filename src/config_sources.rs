@@ -0,0 +1,65 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+
+
+/// # Summary
+/// Builds the config sources from the config file paths, highest priority first. Environment variables always have the highest priority, then later files override earlier ones, so the first file is the base and every following file only needs to contain the keys it overrides. If no config is found at all, only the default file at `config_filepaths[0]` is created, override files are never created.
+///
+/// # Arguments
+/// - `config_filepaths`: config file paths, base first, overrides after
+///
+/// # Returns
+/// - config sources, highest priority first
+pub fn config_sources(config_filepaths: &[String]) -> Vec<load_config::Source>
+{
+    let mut config_sources: Vec<load_config::Source>; // config sources, highest priority first
+
+
+    config_sources = vec![load_config::Source::Env]; // env always has highest priority
+    config_sources.extend(config_filepaths.iter().rev().map(|s| load_config::Source::File(load_config::SourceFile::Toml(s.to_owned())))); // earlier sources have priority, so reverse to let later overrides win over base
+
+    return config_sources;
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::config::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn override_wins_and_base_fills_missing_keys()
+    {
+        let base_filepath: std::path::PathBuf = temp_path("base.toml");
+        let override_filepath: std::path::PathBuf = temp_path("override.toml");
+        let base: Config = Config {INPUT_CALENDAR_URL: "https://base.example/calendar.ics".to_owned(), SLEEP_INTERVAL: 500, ..Config::default()};
+        std::fs::write(&base_filepath, toml::to_string(&base).unwrap()).unwrap(); // complete config
+        std::fs::write(&override_filepath, "SLEEP_INTERVAL = 42\n").unwrap(); // only overrides 1 key
+
+
+        let config: Config = load_config::load_config(config_sources(&[base_filepath.to_string_lossy().into_owned(), override_filepath.to_string_lossy().into_owned()]), None).unwrap();
+
+        assert_eq!(config.SLEEP_INTERVAL, 42); // override wins
+        assert_eq!(config.INPUT_CALENDAR_URL, "https://base.example/calendar.ics"); // base fills keys override lacks
+        assert_eq!(config.OUTPUT_CALENDAR_FILEPATH, base.OUTPUT_CALENDAR_FILEPATH);
+        std::fs::remove_file(base_filepath).unwrap();
+        std::fs::remove_file(override_filepath).unwrap();
+    }
+
+
+    #[test]
+    fn env_has_highest_priority_then_later_files()
+    {
+        let config_sources: Vec<load_config::Source> = config_sources(&["base.toml".to_owned(), "override.toml".to_owned()]);
+
+
+        assert!(matches!(config_sources.as_slice(),
+        [
+            load_config::Source::Env,
+            load_config::Source::File(load_config::SourceFile::Toml(s1)),
+            load_config::Source::File(load_config::SourceFile::Toml(s2)),
+        ] if s1 == "override.toml" && s2 == "base.toml"));
+    }
+}
\ No newline at end of file
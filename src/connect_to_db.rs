@@ -3,19 +3,26 @@ use crate::error::*;
 
 
 /// # Summary
-/// Creates a new database or connects to an existing one at `db_url`, runs the instructions in `migrations_path`, and returns a connection pool.
+/// Creates a new database or connects to an existing one at `db_url`, runs the instructions in `migrations_path`, and returns a connection pool. Every connection uses WAL journal mode and waits up to `db_busy_timeout` for locks, so other processes can read the database while it is written to.
 ///
 /// # Arguments
 /// - `db_url`: url to database file, might not be local but is recommended to be so
 /// - `db_migrations_dir`: directory containing the commands to migrate between database versions
 /// - `db_migrations_version`: version to migrate to
+/// - `db_busy_timeout`: how long to wait for a locked database before failing
 ///
 /// # Returns
 /// - database connection pool or error
-pub fn connect_to_db(db_url: &str, db_migrations_dir: &'static include_dir::Dir<'static>, db_migrations_version: usize) -> Result<r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, ConnectToDbError>
+pub fn connect_to_db(db_url: &str, db_migrations_dir: &'static include_dir::Dir<'static>, db_migrations_version: usize, db_busy_timeout: std::time::Duration) -> Result<r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, ConnectToDbError>
 {
     let migrations: rusqlite_migration::Migrations = rusqlite_migration::Migrations::from_directory(db_migrations_dir).unwrap();
     let db: r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>; // database connection
+    let db_manager = || r2d2_sqlite::SqliteConnectionManager::file(db_url).with_init(move |db_con| // configure every new connection
+    {
+        db_con.busy_timeout(db_busy_timeout)?; // wait for locks instead of failing immediately with SQLITE_BUSY, set first so switching journal mode waits too
+        db_con.pragma_update_and_check(None, "journal_mode", "WAL", |_| Ok(()))?; // readers do not block writer and vice versa, persists in database file
+        return Ok(());
+    });
 
 
     if !std::fs::exists(db_url).unwrap_or(false) // if database does not exist
@@ -31,12 +38,12 @@ pub fn connect_to_db(db_url: &str, db_migrations_dir: &'static include_dir::Dir<
             }
             None => log::warn!("Creating parent directories for new database at \"{db_url}\", because the directory part could not be parsed.\nThis could be expected behaviour, usually if this is a remote pointing URL and not a local filepath. In that case create the parent directories manually."),
         }
-        db = r2d2::Pool::new(r2d2_sqlite::SqliteConnectionManager::file(db_url))?; // create new database and connect to it
+        db = r2d2::Pool::new(db_manager())?; // create new database and connect to it
         log::info!("Created new database at \"{db_url}\".");
     }
     else
     {
-        db = r2d2::Pool::new(r2d2_sqlite::SqliteConnectionManager::file(db_url))?; // connect to existing database
+        db = r2d2::Pool::new(db_manager())?; // connect to existing database
         log::info!("Connected to database at \"{db_url}\".");
     }
 
@@ -45,4 +52,80 @@ pub fn connect_to_db(db_url: &str, db_migrations_dir: &'static include_dir::Dir<
     migrations.to_version(&mut db_con, db_migrations_version)?; // run migrations to specified version to create and update tables
 
     return Ok(db);
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn every_connection_uses_wal_and_busy_timeout()
+    {
+        static DB_MIGRATIONS_DIR: include_dir::Dir = include_dir::include_dir!("./db_migrations/"); // database migrations directory, same as main_inner
+        let dirpath: std::path::PathBuf = temp_path("db");
+        let db_url: String = dirpath.join("db.sqlite").to_string_lossy().into_owned(); // in parent directory that does not exist yet
+
+
+        let db = connect_to_db(db_url.as_str(), &DB_MIGRATIONS_DIR, 4, std::time::Duration::from_millis(1234)).unwrap();
+        let db_cons: Vec<r2d2::PooledConnection<r2d2_sqlite::SqliteConnectionManager>> = (0..2).map(|_| db.get().unwrap()).collect(); // pragmas are set per connection, so check more than 1
+
+        for db_con in db_cons.iter()
+        {
+            assert_eq!(db_con.pragma_query_value(None, "journal_mode", |row| row.get::<usize, String>(0)).unwrap(), "wal");
+            assert_eq!(db_con.pragma_query_value(None, "busy_timeout", |row| row.get::<usize, u64>(0)).unwrap(), 1234);
+        }
+        drop(db_cons);
+        drop(db);
+        let db = connect_to_db(db_url.as_str(), &DB_MIGRATIONS_DIR, 4, std::time::Duration::from_millis(1234)).unwrap(); // existing database
+        assert_eq!(db.get().unwrap().pragma_query_value(None, "journal_mode", |row| row.get::<usize, String>(0)).unwrap(), "wal");
+        drop(db);
+        std::fs::remove_dir_all(dirpath).unwrap();
+    }
+
+
+    #[test]
+    fn reads_and_writes_wait_for_open_write_transaction()
+    {
+        static DB_MIGRATIONS_DIR: include_dir::Dir = include_dir::include_dir!("./db_migrations/"); // database migrations directory, same as main_inner
+        const EVENT_QUERY: &str = "INSERT INTO Event (uid, summary, start_dt, end_dt, location, description) VALUES (?, 'OFF DAY (X)', '2030-01-01T10:00:00+00:00', '2030-01-01T11:00:00+00:00', 'FRA', '');";
+        let dirpath: std::path::PathBuf = temp_path("db_concurrent");
+        let db_url: String = dirpath.join("db.sqlite").to_string_lossy().into_owned(); // file, in-memory databases do not support wal
+
+
+        for (db_busy_timeout, write_succeeds) in [(std::time::Duration::from_secs(5), true), (std::time::Duration::from_millis(1), false)]
+        {
+            let db = connect_to_db(db_url.as_str(), &DB_MIGRATIONS_DIR, 4, db_busy_timeout).unwrap();
+            let db_con_writer = db.get().unwrap();
+            db_con_writer.execute_batch("DELETE FROM Event; BEGIN IMMEDIATE;").unwrap(); // hold write lock
+            db_con_writer.execute(EVENT_QUERY, ("writer",)).unwrap();
+
+            assert_eq!(db.get().unwrap().query_one("SELECT COUNT(*) FROM Event;", (), |row| row.get::<usize, usize>(0)).unwrap(), 0); // reader not blocked, sees last committed state
+            let db_clone = db.clone();
+            let handle: std::thread::JoinHandle<(Result<usize, rusqlite::Error>, std::time::Duration)> = std::thread::spawn(move || // second writer
+            {
+                let db_con = db_clone.get().unwrap();
+                let t_start: std::time::Instant = std::time::Instant::now();
+                return (db_con.execute(EVENT_QUERY, ("other",)), t_start.elapsed());
+            });
+            std::thread::sleep(std::time::Duration::from_millis(300));
+            db_con_writer.execute_batch("COMMIT;").unwrap(); // release write lock
+            let (result, duration) = handle.join().unwrap();
+
+            assert_eq!(result.is_ok(), write_succeeds, "DB_BUSY_TIMEOUT {db_busy_timeout:?}");
+            if write_succeeds
+            {
+                assert!(std::time::Duration::from_millis(200) <= duration); // waited for lock instead of failing immediately
+                assert_eq!(db.get().unwrap().query_one("SELECT COUNT(*) FROM Event;", (), |row| row.get::<usize, usize>(0)).unwrap(), 2);
+            }
+            else
+            {
+                assert!(matches!(result, Err(rusqlite::Error::SqliteFailure(e, _)) if e.code == rusqlite::ErrorCode::DatabaseBusy)); // timeout too short: busy
+            }
+        }
+        std::fs::remove_dir_all(dirpath).unwrap();
+    }
 }
\ No newline at end of file
@@ -0,0 +1,22 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::dateperhapstime_to_string::*;
+use crate::error::*;
+
+
+/// # Summary
+/// Converts a icalendar::DatePerhapsTime to a UTC datetime. Dates without time, meaning all-day events, have no datetime and result in None.
+///
+/// # Arguments
+/// - `dt`: date or perhaps datetime to convert
+///
+/// # Returns
+/// - UTC datetime, None if only date, or error
+pub fn dateperhapstime_to_datetime(dt: icalendar::DatePerhapsTime) -> Result<Option<chrono::DateTime<chrono::Utc>>, DatePerhapsTimeToStringError>
+{
+    if let icalendar::DatePerhapsTime::Date(_) = dt {return Ok(None);} // only date: no datetime
+
+    let dt_str: String = dateperhapstime_to_string(dt, None)?; // reuse timezone handling, floating datetimes have already been converted when saving in database
+    let dt: chrono::NaiveDateTime = chrono::NaiveDateTime::parse_from_str(dt_str.trim_end_matches('Z'), "%Y-%m-%dT%H:%M:%S")
+        .expect(format!("Parsing \"{dt_str}\" to datetime failed even though it should have been properly formatted in dateperhapstime_to_string.").as_str());
+    return Ok(Some(dt.and_utc())); // floating datetimes are assumed utc, same as in dateperhapstime_to_string
+}
\ No newline at end of file
@@ -1,17 +1,18 @@
 // Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
-use std::str::FromStr;
 use crate::error::*;
+use crate::parse_tzid::*;
 
 
 /// # Summary
-/// Converts a icalendar::DatePerhapsTime to a String.
+/// Converts a icalendar::DatePerhapsTime to a String. Floating datetimes are interpreted in `input_timezone` and converted to UTC, if None they are assumed UTC.
 ///
 /// # Arguments
 /// - `dt`: date or perhaps datetime to convert
+/// - `input_timezone`: timezone of floating datetimes, if None assume utc
 ///
 /// # Returns
 /// - String or error
-pub fn dateperhapstime_to_string(dt: icalendar::DatePerhapsTime) -> Result<String, DatePerhapsTimeToStringError>
+pub fn dateperhapstime_to_string(dt: icalendar::DatePerhapsTime, input_timezone: Option<chrono_tz::Tz>) -> Result<String, DatePerhapsTimeToStringError>
 {
     match dt
     {
@@ -20,15 +21,52 @@ pub fn dateperhapstime_to_string(dt: icalendar::DatePerhapsTime) -> Result<Strin
         {
             match dt
             {
-                icalendar::CalendarDateTime::Floating(dt) => return Ok(format!("{}", dt.format("%Y-%m-%dT%H:%M:%S"))), // assume utc
+                icalendar::CalendarDateTime::Floating(dt) =>
+                {
+                    match input_timezone
+                    {
+                        Some(tz) => // input timezone known: convert to utc
+                        {
+                            let utc = dt.and_local_timezone(tz).single().ok_or(DatePerhapsTimeToStringError::LocalTimeMapping{ldt: dt, tz})?.with_timezone(&chrono::Utc); // create local time, then convert to utc
+                            return Ok(format!("{}", utc.format("%Y-%m-%dT%H:%M:%SZ")));
+                        },
+                        None => return Ok(format!("{}", dt.format("%Y-%m-%dT%H:%M:%S"))), // assume utc
+                    }
+                },
                 icalendar::CalendarDateTime::Utc(dt) => return Ok(format!("{}", dt.format("%Y-%m-%dT%H:%M:%SZ"))),
                 icalendar::CalendarDateTime::WithTimezone { date_time: dt, tzid } => // consider timezone
                 {
-                    let tz: chrono_tz::Tz = chrono_tz::Tz::from_str(&tzid)?; // parse timezone
+                    let tz: chrono_tz::Tz = parse_tzid(&tzid)?; // parse timezone, iana or windows
                     let utc = dt.and_local_timezone(tz).single().ok_or(DatePerhapsTimeToStringError::LocalTimeMapping{ldt: dt, tz})?.with_timezone(&chrono::Utc); // create local time, then convert to utc
                     return Ok(format!("{}", utc.format("%Y-%m-%dT%H:%M:%SZ")));
                 },
             }
         },
     }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+
+    /// # Summary
+    /// Creates a floating datetime from "%Y-%m-%dT%H:%M:%S".
+    fn floating(s: &str) -> icalendar::DatePerhapsTime
+    {
+        return icalendar::DatePerhapsTime::DateTime(icalendar::CalendarDateTime::Floating(chrono::NaiveDateTime::parse_from_str(s, "%Y-%m-%dT%H:%M:%S").unwrap()));
+    }
+
+
+    #[test]
+    fn floating_datetimes_use_input_timezone()
+    {
+        assert_eq!(dateperhapstime_to_string(floating("2030-01-01T10:00:00"), Some(chrono_tz::Europe::Berlin)).unwrap(), "2030-01-01T09:00:00Z"); // winter time, +01:00
+        assert_eq!(dateperhapstime_to_string(floating("2030-07-01T10:00:00"), Some(chrono_tz::Europe::Berlin)).unwrap(), "2030-07-01T08:00:00Z"); // summer time, +02:00
+        assert_eq!(dateperhapstime_to_string(floating("2030-07-01T10:00:00"), None).unwrap(), "2030-07-01T10:00:00"); // unset: assume utc
+        assert!(dateperhapstime_to_string(floating("2030-03-31T02:30:00"), Some(chrono_tz::Europe::Berlin)).is_err()); // skipped by dst change: does not exist
+        assert_eq!(dateperhapstime_to_string(icalendar::DatePerhapsTime::DateTime(icalendar::CalendarDateTime::Utc("2030-07-01T10:00:00Z".parse().unwrap())), Some(chrono_tz::Europe::Berlin)).unwrap(), "2030-07-01T10:00:00Z"); // only floating datetimes affected
+    }
 }
\ No newline at end of file
@@ -0,0 +1,75 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+
+
+/// # Summary
+/// Deletes log files in `log_dirpath` older than `log_retention_days` days. The date is parsed from the beginning of the filename, for example "2025-01-31.log" or "2025-01-31T12_00.log". Files without a date are left untouched.
+///
+/// # Arguments
+/// - `log_dirpath`: directory containing the log files
+/// - `log_retention_days`: number of days to keep log files
+///
+/// # Returns
+/// - number of deleted log files or error
+pub fn delete_old_logs(log_dirpath: &str, log_retention_days: u64) -> Result<usize, std::io::Error>
+{
+    let log_retention_end: chrono::NaiveDate = chrono::Utc::now().date_naive() - chrono::Days::new(log_retention_days); // log files dated before this are deleted
+    let mut logs_deleted: usize = 0; // number of deleted log files
+
+
+    for entry in std::fs::read_dir(log_dirpath)?
+    {
+        let entry: std::fs::DirEntry = entry?;
+        let filename: String = entry.file_name().to_string_lossy().into_owned();
+
+        if !entry.file_type()?.is_file() || !filename.ends_with(".log") {continue;} // only log files
+        match filename.get(0..10).and_then(|s| chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").ok()) // parse date from filename
+        {
+            Some(log_date) if log_date < log_retention_end => // too old: delete
+            {
+                std::fs::remove_file(entry.path())?;
+                log::debug!("Deleted old log file \"{}\".", entry.path().display());
+                logs_deleted += 1;
+            },
+            _ => continue, // recent or no date: keep
+        }
+    }
+
+    return Ok(logs_deleted);
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn only_old_dated_log_files_are_deleted()
+    {
+        let log_dirpath: std::path::PathBuf = temp_path("log");
+        let today: chrono::NaiveDate = chrono::Utc::now().date_naive();
+        let filename = |days_ago: u64, suffix: &str| format!("{}{suffix}", (today - chrono::Days::new(days_ago)).format("%Y-%m-%d"));
+        let filenames_kept: Vec<String> = vec![filename(0, ".log"), filename(7, "T12_00.log"), filename(30, ".txt"), "latest.log".to_owned()]; // recent, on retention end, not a log file, no date
+        let filenames_deleted: Vec<String> = vec![filename(8, ".log"), filename(365, "T12_00.log")];
+        std::fs::create_dir_all(log_dirpath.join(filename(30, ".log"))).unwrap(); // directory named like old log file
+        for filename in filenames_kept.iter().chain(filenames_deleted.iter())
+        {
+            std::fs::write(log_dirpath.join(filename), "").unwrap();
+        }
+
+
+        assert_eq!(delete_old_logs(log_dirpath.to_string_lossy().as_ref(), 7).unwrap(), 2);
+        for filename in filenames_kept.iter()
+        {
+            assert!(log_dirpath.join(filename).exists(), "\"{filename}\" deleted");
+        }
+        for filename in filenames_deleted.iter()
+        {
+            assert!(!log_dirpath.join(filename).exists(), "\"{filename}\" kept");
+        }
+        assert!(log_dirpath.join(filename(30, ".log")).is_dir()); // directories untouched
+        std::fs::remove_dir_all(log_dirpath).unwrap();
+    }
+}
\ No newline at end of file
@@ -0,0 +1,167 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::config::*;
+use crate::error::*;
+
+
+/// # Summary
+/// Downloads calendar from myTime and parses it. Kept separate from updating the database so everything after the download also works without network access.
+///
+/// # Arguments
+/// - `http_client`: http client
+/// - `input_calendar_url`: calendar source URL
+/// - `input_calendar_auth`: authentication for calendar source, if any
+///
+/// # Returns
+/// - downloaded and parsed calendar or error
+pub fn download_calendar(http_client: &reqwest::blocking::Client, input_calendar_url: &str, input_calendar_auth: Option<&InputCalendarAuth>) -> Result<icalendar::Calendar, DownloadCalendarError>
+{
+    let input_calendar: icalendar::Calendar; // input calendar
+
+
+    let mut request = http_client.get(input_calendar_url); // prepare calendar download
+    match input_calendar_auth // add authentication if configured, never log credentials
+    {
+        Some(InputCalendarAuth::Basic {user, pass}) => request = request.basic_auth(user, Some(pass)),
+        Some(InputCalendarAuth::Bearer(token)) => request = request.bearer_auth(token),
+        None => {},
+    }
+    let r = request.send()?; // download calendar ics
+    log::debug!("{}", r.status());
+    let charset: Option<String> = r.headers().get(reqwest::header::CONTENT_TYPE) // charset from content type header, for example "text/calendar; charset=windows-1252"
+        .and_then(|content_type| content_type.to_str().ok())
+        .and_then(|content_type| content_type.split(';').skip(1).find_map(|parameter| parameter.trim().strip_prefix("charset=").map(|s| s.trim_matches('"').to_owned())));
+    let input_calendar_bytes = r.bytes()?; // decode manually, reqwest does not decode charsets without its charset feature
+    let encoding: &'static encoding_rs::Encoding = charset.as_deref().and_then(|s| encoding_rs::Encoding::for_label(s.as_bytes())).unwrap_or(encoding_rs::UTF_8); // if charset unknown or missing default to utf-8
+    let (mut input_calendar_str, mut encoding_used, mut decoding_failed) = encoding.decode(&input_calendar_bytes); // decode, byte order mark takes precedence
+    if decoding_failed && charset.is_none() // no charset given and not valid utf-8: likely windows-1252 export
+    {
+        log::warn!("Input calendar has no charset and is not valid UTF-8. Decoding as Windows-1252.");
+        (input_calendar_str, encoding_used, decoding_failed) = encoding_rs::WINDOWS_1252.decode(&input_calendar_bytes);
+    }
+    log::debug!("Decoded input calendar as {}.", encoding_used.name()); // might differ from charset due to byte order mark
+    if decoding_failed {log::warn!("Decoding input calendar as {} failed partially. Invalid characters have been replaced.", encoding_used.name());}
+    input_calendar = input_calendar_str.parse().map_err(|e: String| match locate_parse_error(&input_calendar_str) // parse calendar ics, library error has no position, so locate offending line for reporting upstream
+    {
+        Some((line_number, line)) => DownloadCalendarError::Parse(format!("{e}\nOffending line {line_number}: \"{line}\"")),
+        None => DownloadCalendarError::Parse(e),
+    })?;
+    log::info!("Downloaded and parsed calendar from \"{input_calendar_url}\"."); // log download
+    log::debug!("{input_calendar}");
+
+    return Ok(input_calendar);
+}
+
+
+/// # Summary
+/// Locates the first line of a calendar ics that is not a valid content line or closes a component that is not open. Folded continuation lines and empty lines are skipped. If a component is never closed, its opening line is returned.
+///
+/// # Arguments
+/// - `input_calendar_str`: calendar ics
+///
+/// # Returns
+/// - 1-based line number and line, shortened to 80 characters, or None if no offending line was found
+fn locate_parse_error(input_calendar_str: &str) -> Option<(usize, String)>
+{
+    let mut components_open: Vec<(usize, &str, &str)> = Vec::new(); // open components with their opening line number, name, and line
+    let shorten = |line: &str| line.chars().take(80).collect::<String>(); // keep error messages readable
+
+
+    for (i, line) in input_calendar_str.split('\n').map(|line| line.trim_end_matches('\r')).enumerate()
+    {
+        if line.is_empty() || line.starts_with([' ', '\t']) {continue;} // empty or continuation of folded line: nothing to check
+        let name: &str = &line[..line.find([';', ':']).unwrap_or(line.len())]; // property name ends at parameters or value
+        if name.is_empty() || !name.chars().all(|c| c.is_ascii_alphanumeric() || c == '-') || !line.contains(':') // invalid content line
+        {
+            return Some((i + 1, shorten(line)));
+        }
+        match (name.to_uppercase().as_str(), line.split_once(':').map(|(_, value)| value.trim())) // track components
+        {
+            ("BEGIN", Some(value)) => components_open.push((i + 1, value, line)),
+            ("END", Some(value)) =>
+            {
+                match components_open.pop()
+                {
+                    Some((_, component, _)) if component.eq_ignore_ascii_case(value) => {}, // closes the open component
+                    _ => return Some((i + 1, shorten(line))), // closes a component that is not open
+                }
+            },
+            _ => {},
+        }
+    }
+
+    return components_open.pop().map(|(line_number, _, line)| (line_number, shorten(line))); // never closed component
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use icalendar::Component;
+    use crate::build_http_client::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn basic_auth_is_sent()
+    {
+        let (url, requests) = serve(vec![(Some("text/calendar"), calendar_minimal().into_bytes())]);
+        let input_calendar_auth: InputCalendarAuth = InputCalendarAuth::Basic {user: "user".to_owned(), pass: "pass".to_owned()};
+
+
+        download_calendar(&build_http_client(&Config::default()).unwrap(), url.as_str(), Some(&input_calendar_auth)).unwrap();
+
+        assert_eq!(request_header(&requests.recv().unwrap(), "Authorization"), Some("Basic dXNlcjpwYXNz".to_owned())); // base64 of "user:pass"
+    }
+
+
+    #[test]
+    fn bearer_auth_is_sent()
+    {
+        let (url, requests) = serve(vec![(Some("text/calendar"), calendar_minimal().into_bytes())]);
+        let input_calendar_auth: InputCalendarAuth = InputCalendarAuth::Bearer("token123".to_owned());
+
+
+        download_calendar(&build_http_client(&Config::default()).unwrap(), url.as_str(), Some(&input_calendar_auth)).unwrap();
+
+        assert_eq!(request_header(&requests.recv().unwrap(), "Authorization"), Some("Bearer token123".to_owned()));
+    }
+
+
+    #[test]
+    fn no_auth_sends_no_authorization()
+    {
+        let (url, requests) = serve(vec![(Some("text/calendar"), calendar_minimal().into_bytes())]);
+
+
+        download_calendar(&build_http_client(&Config::default()).unwrap(), url.as_str(), None).unwrap();
+
+        assert_eq!(request_header(&requests.recv().unwrap(), "Authorization"), None);
+    }
+
+
+    #[test]
+    fn windows_1252_is_decoded()
+    {
+        let input_calendar_bytes: Vec<u8> = encoding_rs::WINDOWS_1252.encode(calendar_minimal().replace("OFF DAY (X)", "HTL (Hôtel Café)").as_str()).0.into_owned(); // "ô" and "é" are single bytes, invalid utf-8
+        let (url, _requests) = serve(vec![(Some("text/calendar"), input_calendar_bytes.clone()), (Some("text/calendar; charset=\"windows-1252\""), input_calendar_bytes)]);
+        let http_client: reqwest::blocking::Client = build_http_client(&Config::default()).unwrap();
+        let summary = |input_calendar: icalendar::Calendar| input_calendar.iter().find_map(|component| component.as_event()).and_then(|calendar_event| calendar_event.get_summary().map(|s| s.to_owned()));
+
+
+        assert_eq!(summary(download_calendar(&http_client, url.as_str(), None).unwrap()), Some("HTL (Hôtel Café)".to_owned())); // no charset: fallback
+        assert_eq!(summary(download_calendar(&http_client, url.as_str(), None).unwrap()), Some("HTL (Hôtel Café)".to_owned())); // charset given
+    }
+
+
+    #[test]
+    fn parse_error_reports_offending_line()
+    {
+        assert_eq!(locate_parse_error(&calendar_minimal()), None);
+        assert_eq!(locate_parse_error(&calendar_minimal().replace("SUMMARY:OFF DAY (X)\r\n", "SUMMARY:OFF DAY\r\n (X)\r\n\r\n")), None); // folded and empty lines are fine
+        assert_eq!(locate_parse_error(&calendar_minimal().replace("SUMMARY:OFF DAY (X)", "SUMMARY OFF DAY (X)")), Some((9, "SUMMARY OFF DAY (X)".to_owned()))); // malformed line
+        assert_eq!(locate_parse_error(&calendar_minimal().replace("END:VEVENT", "END:VTODO")), Some((10, "END:VTODO".to_owned()))); // closes component that is not open
+        assert_eq!(locate_parse_error(&calendar_minimal().replace("END:VCALENDAR\r\n", "")), Some((1, "BEGIN:VCALENDAR".to_owned()))); // never closed
+        assert_eq!(locate_parse_error(&calendar_minimal().replace("SUMMARY:OFF DAY (X)", format!("SUMMARY {}", "X".repeat(100)).as_str())).map(|(_, line)| line.chars().count()), Some(80)); // shortened
+    }
+}
\ No newline at end of file
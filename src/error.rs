@@ -1,6 +1,17 @@
 // Copyright (c) 2024 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
 
 
+#[derive(Debug, thiserror::Error)]
+pub enum CheckDbError
+{
+    #[error("Checking airport database failed with: {0}")]
+    R2d2(#[from] r2d2::Error),
+
+    #[error("Checking airport database failed with: {0}")]
+    Rusqlite(#[from] rusqlite::Error),
+}
+
+
 #[derive(Debug, thiserror::Error)]
 pub enum ConnectToDbError
 {
@@ -23,19 +34,78 @@ pub enum DatePerhapsTimeToStringError
 }
 
 
+#[derive(Debug, thiserror::Error)]
+pub enum DownloadCalendarError
+{
+    #[error("Parsing input calendar failed with: {0}")]
+    Parse(String), // icalendar parse error
+
+    #[error("Downloading input calendar from \"{}\" failed with: {}", .0.url().map_or_else(|| "<unknown>", |o| o.as_str()), .0)]
+    Reqwest(#[from] reqwest::Error), // reqwest error
+}
+
+
+/// # Summary
+/// Top-level error, each variant belongs to a failure class with its own exit code:
+/// - 1: panic
+/// - 2: invalid command line arguments, not an Error variant, set in main
+/// - 3: config error, AlarmEmailMissing, ExcludedEventTypeUnknown, and config or pattern loading in main, including the first run creating the default config file, which previously exited with 1
+/// - 4: network error, Reqwest
+/// - 5: database error, CheckDb, ConnectToDb, DbImplausible, ResetDb, Rusqlite
+///
+/// At the top level Reqwest only occurs when building the http client fails, for example because the tls backend could not be initialised. Network errors while downloading the calendar or updating airports are logged and retried next iteration, so they never end the program.
 #[derive(Debug, thiserror::Error)]
 pub enum Error
 {
+    #[error("ALARM_ACTION is \"email\", but ALARM_EMAIL is unset.")]
+    AlarmEmailMissing, // email alarms need a recipient
+
+    #[error("{0}")]
+    CheckDb(#[from] CheckDbError), // check database error
+
     #[error("{0}")]
     ConnectToDb(#[from] ConnectToDbError),
 
+    #[error("Airport database is implausible: {0}")]
+    DbImplausible(String), // row counts too low, update incomplete or failed
+
+    #[error("EXCLUDED_EVENT_TYPES contains unknown event type \"{0}\".")]
+    ExcludedEventTypeUnknown(String), // probably a typo, would silently exclude nothing
+
     #[error("Creating http client failed with: {0}")]
     Reqwest(#[from] reqwest::Error), // reqwest error
 
+    #[error("{0}")]
+    ResetDb(#[from] ResetDbError), // reset database error
+
     #[error("Disconnecting from database failed with: {0}")]
     Rusqlite(#[from] rusqlite::Error),
 }
 
+impl Error
+{
+    pub const EXIT_CODE_CLI: u8 = 2; // invalid command line arguments
+    pub const EXIT_CODE_CONFIG: u8 = 3; // config error
+    pub const EXIT_CODE_DB: u8 = 5; // database error
+    pub const EXIT_CODE_NETWORK: u8 = 4; // network error
+
+
+    /// # Summary
+    /// Returns the exit code of the error's failure class, so orchestration can react differently to each class.
+    ///
+    /// # Returns
+    /// - exit code
+    pub fn exit_code(&self) -> u8
+    {
+        match self
+        {
+            Self::AlarmEmailMissing | Self::ExcludedEventTypeUnknown(_) => return Self::EXIT_CODE_CONFIG,
+            Self::CheckDb(_) | Self::ConnectToDb(_) | Self::DbImplausible(_) | Self::ResetDb(_) | Self::Rusqlite(_) => return Self::EXIT_CODE_DB,
+            Self::Reqwest(_) => return Self::EXIT_CODE_NETWORK,
+        }
+    }
+}
+
 
 #[derive(Debug, thiserror::Error)]
 pub enum LoadCalendarError
@@ -48,6 +118,45 @@ pub enum LoadCalendarError
 }
 
 
+#[derive(Debug, thiserror::Error)]
+pub enum LoadEventTypePatternsError
+{
+    #[error("Pattern of event type \"{event_type}\" is missing capture group \"{capture_group}\".")]
+    CaptureGroupMissing {event_type: String, capture_group: String}, // transformation needs data from this capture group
+
+    #[error("Compiling pattern of event type \"{event_type}\" failed with: {source}")]
+    Regex {event_type: String, source: regex::Error}, // invalid regex
+
+    #[error("Loading event type patterns failed with: {0}")]
+    StdIo(#[from] std::io::Error),
+
+    #[error("Parsing event type patterns failed with: {0}")]
+    TomlDe(#[from] toml::de::Error),
+
+    #[error("Event type patterns contain unknown event type \"{event_type}\".")]
+    UnknownEventType {event_type: String}, // probably a typo
+}
+
+
+#[derive(Debug, thiserror::Error)]
+pub enum ParseCliActionError
+{
+    #[error("Command line arguments \"{0}\" are invalid.")]
+    InvalidArguments(String), // unknown argument or wrong number of arguments
+}
+
+
+#[derive(Debug, thiserror::Error)]
+pub enum ResetDbError
+{
+    #[error("Resetting event database failed with: {0}")]
+    R2d2(#[from] r2d2::Error),
+
+    #[error("Resetting event database failed with: {0}")]
+    Rusqlite(#[from] rusqlite::Error),
+}
+
+
 #[derive(Debug, thiserror::Error)]
 pub enum UpdateAirportsError
 {
@@ -65,6 +174,9 @@ pub enum UpdateAirportsError
 #[derive(Debug, thiserror::Error)]
 pub enum UpdateCalendarError
 {
+    #[error("{0}")]
+    DownloadCalendar(#[from] DownloadCalendarError), // download calendar error
+
     #[error("{0}")]
     LoadCalendar(#[from] LoadCalendarError), // load calendar error
 
@@ -93,12 +205,6 @@ pub enum UpdateCountriesError
 #[derive(Debug, thiserror::Error)]
 pub enum UpdateEventsError
 {
-    #[error("Parsing input calendar failed with: {0}")]
-    Parse(String), // icalendar parse error
-
-    #[error("Downloading input calendar from \"{}\" failed with: {}", .0.url().map_or_else(|| "<unknown>", |o| o.as_str()), .0)]
-    Reqwest(#[from] reqwest::Error), // reqwest error
-
     #[error("Updating events in database failed with: {0}")]
     R2d2(#[from] r2d2::Error),
 
@@ -106,10 +212,28 @@ pub enum UpdateEventsError
     Rusqlite(#[from] rusqlite::Error),
 }
 
-impl From<String> for UpdateEventsError
+
+#[cfg(test)]
+mod tests
 {
-    fn from(s: String) -> Self
+    use super::*;
+
+
+    #[test]
+    fn exit_code_per_failure_class()
     {
-        Self::Parse(s) // #[from] does not like String, that's why this is needed
+        let reqwest_error: reqwest::Error = reqwest::blocking::Client::builder().user_agent("invalid\n").build().unwrap_err(); // any error building the http client
+
+
+        assert_eq!(Error::AlarmEmailMissing.exit_code(), Error::EXIT_CODE_CONFIG);
+        assert_eq!(Error::ExcludedEventTypeUnknown("Flight".to_owned()).exit_code(), Error::EXIT_CODE_CONFIG);
+        assert_eq!(Error::Reqwest(reqwest_error).exit_code(), Error::EXIT_CODE_NETWORK);
+
+        assert_eq!(Error::CheckDb(CheckDbError::Rusqlite(rusqlite::Error::QueryReturnedNoRows)).exit_code(), Error::EXIT_CODE_DB);
+        assert_eq!(Error::ConnectToDb(ConnectToDbError::RusqliteMigration(rusqlite_migration::Error::RusqliteError {query: String::new(), err: rusqlite::Error::QueryReturnedNoRows})).exit_code(), Error::EXIT_CODE_DB);
+        assert_eq!(Error::DbImplausible("0 airports".to_owned()).exit_code(), Error::EXIT_CODE_DB);
+        assert_eq!(Error::ResetDb(ResetDbError::Rusqlite(rusqlite::Error::QueryReturnedNoRows)).exit_code(), Error::EXIT_CODE_DB);
+        assert_eq!(Error::Rusqlite(rusqlite::Error::QueryReturnedNoRows).exit_code(), Error::EXIT_CODE_DB);
+        assert_eq!(std::collections::HashSet::from([Error::EXIT_CODE_CLI, Error::EXIT_CODE_CONFIG, Error::EXIT_CODE_DB, Error::EXIT_CODE_NETWORK]).len(), 4); // distinct per class
     }
 }
\ No newline at end of file
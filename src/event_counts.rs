@@ -0,0 +1,89 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use icalendar::EventLike;
+use crate::dateperhapstime_to_string::*;
+use crate::event_type::*;
+use crate::is_archived::*;
+
+
+/// # Summary
+/// Number of events per event type in one calendar update, for a concise summary after each iteration.
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+pub struct EventCounts
+{
+    pub archived: usize, // number of archived events
+    pub per_event_type: std::collections::BTreeMap<&'static str, usize>, // number of events per event type name, sorted for stable output
+    pub total: usize, // number of events
+}
+
+impl EventCounts
+{
+    /// # Summary
+    /// Counts the events per event type and how many of them are archived.
+    ///
+    /// # Arguments
+    /// - `calendar_events`: calendar events with their event types
+    /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+    ///
+    /// # Returns
+    /// - event counts
+    pub fn count(calendar_events: &[(EventType, icalendar::Event)], archive_end_dt: &chrono::DateTime<chrono::Utc>) -> Self
+    {
+        let mut event_counts: Self = Self::default(); // event counts
+
+
+        for (event_type, calendar_event) in calendar_events
+        {
+            event_counts.total += 1;
+            *event_counts.per_event_type.entry(event_type.name()).or_insert(0) += 1;
+            if let Some(Ok(true)) = calendar_event.get_end().and_then(|dt| dateperhapstime_to_string(dt, None).ok()).map(|dt_str| is_archived(dt_str.as_str(), archive_end_dt)) // if end invalid: not counted as archived
+            {
+                event_counts.archived += 1;
+            }
+        }
+
+        return event_counts;
+    }
+}
+
+impl std::fmt::Display for EventCounts
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{} events ({}), {} archived, {} unknown",
+            self.total,
+            self.per_event_type.iter().map(|(event_type_name, count)| format!("{count} {event_type_name}")).collect::<Vec<String>>().join(", "),
+            self.archived,
+            self.per_event_type.get("Unknown").unwrap_or(&0) + self.per_event_type.get("NoSummary").unwrap_or(&0), // malformed events are unknown as well
+        )
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn events_are_counted_per_event_type()
+    {
+        let calendar_events: Vec<(EventType, icalendar::Event)> = vec!
+        [
+            (EventType::Off, test_event("OFF DAY (X)", "1999-01-01", "1999-01-02", "FRA")), // archived
+            (EventType::Off, test_event("OFF DAY (X)", "2030-01-01", "2030-01-02", "FRA")),
+            (EventType::Layover, test_event("LAYOVER", "2030-01-02T10:00:00Z", "2030-01-02T20:00:00Z", "FRA")),
+            (EventType::NoSummary, test_event("", "2030-01-03T10:00:00Z", "2030-01-03T11:00:00Z", "FRA")),
+            (EventType::Unknown, test_event("SOMETHING NEW", "2030-01-04T10:00:00Z", "2030-01-04T11:00:00Z", "FRA")),
+        ];
+
+
+        let event_counts: EventCounts = EventCounts::count(&calendar_events, &archive_end_dt());
+
+        assert_eq!(event_counts.total, 5);
+        assert_eq!(event_counts.archived, 1);
+        assert_eq!(event_counts.per_event_type, std::collections::BTreeMap::from([("Layover", 1), ("NoSummary", 1), ("Off", 2), ("Unknown", 1)]));
+        assert_eq!(event_counts.to_string(), "5 events (1 Layover, 1 NoSummary, 2 Off, 1 Unknown), 1 archived, 2 unknown"); // malformed counted as unknown
+    }
+}
\ No newline at end of file
@@ -1,8 +1,10 @@
 // Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use regex_automata::dfa::Automaton;
+use crate::event_type_patterns::*;
 
 
 /// # Summary
-/// Types of events the duty plan can have. Each event defines a transformation to be made for the output calendar, "Rest" means nothing is changed. Contains the regex patterns to match the events.
+/// Types of events the duty plan can have. Each event defines a transformation to be made for the output calendar, "Rest" means nothing is changed. The regex patterns to match the events are in `EventTypePatterns`.
 #[derive(Clone, Debug)]
 pub enum EventType
 {
@@ -11,7 +13,9 @@ pub enum EventType
     Flight {flight_iata: String, departure_iata: String, destination_iata: String}, // flight from A to B
     Ground {category: String, description: String}, // ground event like simulator, classroom
     Holiday, // holiday
+    Hotel {name: String}, // hotel stay
     Layover, // layover somewhere else
+    NoSummary, // event without summary, source event is malformed, only do minimum
     Off, // free day
     Pickup, // hotel pickup
     Reserve {description: String}, // reserve duty
@@ -26,66 +30,53 @@ impl EventType
     ///
     /// # Arguments
     /// - `calendar_event_summary`: the summary of the calendar event to determine the event type of
+    /// - `event_type_patterns`: compiled patterns to match the summary against
     ///
     /// # Returns
     /// - the determined event type or `DutyPlanEvent::Default` if the event type could not be determined
-    pub fn determine_event_type(calendar_event_summary: String) -> Self
+    pub fn determine_event_type(calendar_event_summary: String, event_type_patterns: &EventTypePatterns) -> Self
     {
-        const BRIEFING_PATTERN: &str = r"^(\d{2}:\d{2} LT BRIEFING [A-Z]{3})$";
-        const DEADHEAD_PATTERN: &str = r"^(DH (?P<flight_iata>[\dA-Z][A-Z] \d{1,4}): (?P<departure_iata>[A-Z]{3})-(?P<destination_iata>[A-Z]{3}))$";
-        const FLIGHT_PATTERN: &str = r"^((?P<flight_iata>[\dA-Z][A-Z] \d{1,4}): (?P<departure_iata>[A-Z]{3})-(?P<destination_iata>[A-Z]{3}))$";
-        const GROUND_PATTERN: &str = r"^((?P<category>GENERALEVENT|MANDATORY TRAINING|MEDICAL EVENT|OFFICE DAY|SIMULATOR) \((?P<description>.+)\))$";
-        const HOLIDAY_PATTERN: &str = r"^(ABSENCE \(.+\))$";
-        const LAYOVER_PATTERN: &str = r"^(LAYOVER( \[[A-Z]{3}\])?)$";
-        const OFF_PATTERN: &str = r"^(OFF DAY \(.+\))$";
-        const PICKUP_PATTERN: &str = r"^(\d{2}:\d{2} LT PICKUP [A-Z]{3})$";
-        const RESERVE_PATTERN: &str = r"^((RESERVE|STANDBY) \((?P<description>RB(_[0-9]+)?|RES|REP|SB(_[A-Z_]+)?)\))$";
-        const SICKNESS_PATTERN: &str = r"^(SICKNESS \(K(O)?\))$";
-
-
-        if regex::Regex::new(BRIEFING_PATTERN).expect("Compiling briefing regex failed.").is_match(calendar_event_summary.to_uppercase().as_str())
+        if event_type_patterns.briefing.is_match(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Briefing;
         }
-        else if let Some(captures) = regex::Regex::new(DEADHEAD_PATTERN).expect("Compiling deadhead regex failed.").captures(calendar_event_summary.to_uppercase().as_str())
+        else if let Some(captures) = event_type_patterns.deadhead.captures(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Deadhead {flight_iata: captures["flight_iata"].replace(" ", ""), departure_iata: captures["departure_iata"].to_owned(), destination_iata: captures["destination_iata"].to_owned()}; // remove spaces from flight number
         }
-        else if let Some(captures) = regex::Regex::new(FLIGHT_PATTERN).expect("Compiling flight regex failed.").captures(calendar_event_summary.to_uppercase().as_str())
+        else if let Some(captures) = event_type_patterns.flight.captures(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Flight {flight_iata: captures["flight_iata"].replace(" ", ""), departure_iata: captures["departure_iata"].to_owned(), destination_iata: captures["destination_iata"].to_owned()}; // remove spaces from flight number
         }
-        else if let Some(captures) = regex::Regex::new(GROUND_PATTERN).expect("Compiling ground regex failed.").captures(calendar_event_summary.to_uppercase().as_str())
+        else if let Some(captures) = event_type_patterns.ground.captures(calendar_event_summary.to_uppercase().as_str())
         {
-            let category_mapping: std::collections::HashMap<&str, &str> = std::collections::HashMap::from
-            ([
-                ("GeneralEvent", ""), // unnecessary, no information value with this
-                ("Mandatory Training", "Training"),
-                ("Medical Event", "Medical")
-            ]); // map categories to shorter and prettier versions, if not in here forward category unchanged
-            return Self::Ground {category: category_mapping.get(&captures["category"]).unwrap_or(&&captures["category"]).to_string(), description: captures["description"].to_owned()};
+            return Self::Ground {category: captures["category"].to_owned(), description: captures["description"].to_owned()}; // category is mapped in transformation, so mapping is configurable
         }
-        else if regex::Regex::new(HOLIDAY_PATTERN).expect("Compiling holiday regex failed.").is_match(calendar_event_summary.to_uppercase().as_str())
+        else if event_type_patterns.holiday.is_match(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Holiday;
         }
-        else if regex::Regex::new(LAYOVER_PATTERN).expect("Compiling layover regex failed.").is_match(calendar_event_summary.to_uppercase().as_str())
+        else if let Some(captures) = event_type_patterns.hotel.captures(calendar_event_summary.as_str())
+        {
+            return Self::Hotel {name: captures["name"].to_owned()};
+        }
+        else if event_type_patterns.layover.is_match(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Layover;
         }
-        else if regex::Regex::new(OFF_PATTERN).expect("Compiling off regex failed.").is_match(calendar_event_summary.to_uppercase().as_str())
+        else if event_type_patterns.off.is_match(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Off;
         }
-        else if regex::Regex::new(PICKUP_PATTERN).expect("Compiling pickup regex failed.").is_match(calendar_event_summary.to_uppercase().as_str())
+        else if event_type_patterns.pickup.is_match(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Pickup;
         }
-        else if let Some(captures) = regex::Regex::new(RESERVE_PATTERN).expect("Compiling pickup regex failed.").captures(calendar_event_summary.to_uppercase().as_str())
+        else if let Some(captures) = event_type_patterns.reserve.captures(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Reserve {description: captures["description"].to_owned()};
         }
-        else if regex::Regex::new(SICKNESS_PATTERN).expect("Compiling sickness regex failed.").is_match(calendar_event_summary.to_uppercase().as_str())
+        else if event_type_patterns.sickness.is_match(calendar_event_summary.to_uppercase().as_str())
         {
             return Self::Sickness;
         }
@@ -95,4 +86,196 @@ impl EventType
             return Self::Unknown;
         }
     }
+
+
+    /// # Summary
+    /// Determine the event type of a calendar event based on its summary like `determine_event_type`. If the event type could not be determined, additionally diagnoses which pattern came closest to matching, to help crafting new patterns.
+    ///
+    /// # Arguments
+    /// - `calendar_event_summary`: the summary of the calendar event to determine the event type of
+    /// - `event_type_patterns`: compiled patterns to match the summary against
+    ///
+    /// # Returns
+    /// - the determined event type and, only if unknown, the diagnosis
+    pub fn determine_event_type_verbose(calendar_event_summary: String, event_type_patterns: &EventTypePatterns) -> (Self, Option<UnknownDiagnosis>)
+    {
+        let event_type: Self = Self::determine_event_type(calendar_event_summary.clone(), event_type_patterns); // determine normally
+        let mut unknown_diagnosis: UnknownDiagnosis = UnknownDiagnosis {closest_pattern: "", matched: String::new(), patterns_tried: Vec::new()}; // diagnosis
+        let summary_uppercase: String = calendar_event_summary.to_uppercase(); // all patterns except hotel are matched against uppercased summary
+
+
+        if !matches!(event_type, Self::Unknown) {return (event_type, None);} // determined: nothing to diagnose
+
+        for (event_type_name, _) in event_type_patterns.all()
+        {
+            let summary: &str = if event_type_name == "Hotel" {calendar_event_summary.as_str()} else {summary_uppercase.as_str()}; // same casing as in determine_event_type
+            let Some(dfa) = event_type_patterns.automata.get(event_type_name) else {continue;}; // custom patterns with unicode word boundaries have no automaton and can not be diagnosed
+            let Ok(mut state) = dfa.start_state_forward(&regex_automata::Input::new(summary).anchored(regex_automata::Anchored::Yes)) else {continue;}; // start at beginning of summary
+            let mut matched_len: usize = 0; // length of longest summary prefix the pattern could still complete to a match
+
+
+            for (i, byte) in summary.bytes().enumerate()
+            {
+                state = dfa.next_state(state, byte);
+                if dfa.is_dead_state(state) {break;} // pattern can not match anymore
+                matched_len = i + 1;
+            }
+            if unknown_diagnosis.matched.len() < matched_len || unknown_diagnosis.closest_pattern.is_empty() // longer prefix than before, on tie first pattern wins
+            {
+                unknown_diagnosis.closest_pattern = event_type_name;
+                unknown_diagnosis.matched = String::from_utf8_lossy(&summary.as_bytes()[..matched_len]).into_owned(); // might cut utf8 char in half, lossy to be safe
+            }
+            unknown_diagnosis.patterns_tried.push(event_type_name);
+        }
+
+        return (event_type, Some(unknown_diagnosis));
+    }
+
+
+    /// # Summary
+    /// Returns the names of all event types, for example to validate configured event type names.
+    ///
+    /// # Returns
+    /// - names of all event types
+    pub fn names() -> [&'static str; 13]
+    {
+        return ["Briefing", "Deadhead", "Flight", "Ground", "Holiday", "Hotel", "Layover", "NoSummary", "Off", "Pickup", "Reserve", "Sickness", "Unknown"];
+    }
+
+
+    /// # Summary
+    /// Returns the name of the event type without its data, for example to compare it against configured event type names.
+    ///
+    /// # Returns
+    /// - name of the event type
+    pub fn name(&self) -> &'static str
+    {
+        match self
+        {
+            Self::Briefing => return "Briefing",
+            Self::Deadhead {..} => return "Deadhead",
+            Self::Flight {..} => return "Flight",
+            Self::Ground {..} => return "Ground",
+            Self::Holiday => return "Holiday",
+            Self::Hotel {..} => return "Hotel",
+            Self::Layover => return "Layover",
+            Self::NoSummary => return "NoSummary",
+            Self::Off => return "Off",
+            Self::Pickup => return "Pickup",
+            Self::Reserve {..} => return "Reserve",
+            Self::Sickness => return "Sickness",
+            Self::Unknown => return "Unknown",
+        }
+    }
+
+
+    /// # Summary
+    /// Checks if the event type is in `excluded_event_types`, case-insensitive.
+    ///
+    /// # Arguments
+    /// - `excluded_event_types`: names of excluded event types, usually `EXCLUDED_EVENT_TYPES`
+    ///
+    /// # Returns
+    /// - whether the event type is excluded
+    pub fn is_excluded(&self, excluded_event_types: &[String]) -> bool
+    {
+        return excluded_event_types.iter().any(|excluded_event_type| excluded_event_type.eq_ignore_ascii_case(self.name()));
+    }
+}
+
+
+/// # Summary
+/// Diagnosis why a summary could not be assigned an event type.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct UnknownDiagnosis
+{
+    pub closest_pattern: &'static str, // name of event type whose pattern matched the longest summary prefix
+    pub matched: String, // longest summary prefix the closest pattern could still complete to a match, uppercase unless closest pattern is hotel
+    pub patterns_tried: Vec<&'static str>, // names of event types whose patterns were tried
+}
+
+impl std::fmt::Display for UnknownDiagnosis
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "Tried patterns: {}\nClosest pattern: {} (matched \"{}\")", self.patterns_tried.join(", "), self.closest_pattern, self.matched)
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn built_in_patterns_classify_every_event_type()
+    {
+        let event_type_patterns: EventTypePatterns = patterns_default();
+
+
+        for (summary, name) in
+        [
+            ("06:15 LT Briefing FRA", "Briefing"), // case-insensitive
+            ("DH LH 100: FRA-MUC", "Deadhead"),
+            ("LH 100: FRA-MUC", "Flight"),
+            ("SIMULATOR (A320 SIM)", "Ground"),
+            ("ABSENCE (URLAUB)", "Holiday"),
+            ("HTL (Maritim Hotel)", "Hotel"),
+            ("LAYOVER [MUC]", "Layover"),
+            ("OFF DAY (X)", "Off"),
+            ("07:00 LT PICKUP FRA", "Pickup"),
+            ("RESERVE (RES)", "Reserve"),
+            ("SICKNESS (KO)", "Sickness"),
+            ("SOMETHING NEW", "Unknown"),
+        ]
+        {
+            assert_eq!(EventType::determine_event_type(summary.to_owned(), &event_type_patterns).name(), name, "summary \"{summary}\"");
+        }
+    }
+
+
+    #[test]
+    fn captures_are_extracted()
+    {
+        let event_type_patterns: EventTypePatterns = patterns_default();
+
+
+        assert!(matches!(EventType::determine_event_type("lh 100: fra-muc".to_owned(), &event_type_patterns), EventType::Flight {flight_iata, departure_iata, destination_iata} if flight_iata == "LH100" && departure_iata == "FRA" && destination_iata == "MUC")); // uppercased, spaces removed from flight number
+        assert!(matches!(EventType::determine_event_type("HTL (Maritim Hotel)".to_owned(), &event_type_patterns), EventType::Hotel {name} if name == "Maritim Hotel")); // hotel name keeps case
+        assert!(matches!(EventType::determine_event_type("STANDBY (SB_HOME)".to_owned(), &event_type_patterns), EventType::Reserve {description} if description == "SB_HOME"));
+    }
+
+
+    #[test]
+    fn classify_diagnoses_unknown_only()
+    {
+        let event_type_patterns: EventTypePatterns = patterns_default();
+
+
+        assert!(EventType::determine_event_type_verbose("OFF DAY (X)".to_owned(), &event_type_patterns).1.is_none()); // known: nothing to diagnose
+        let (event_type, unknown_diagnosis) = EventType::determine_event_type_verbose("LH 100: FRA-MUCX".to_owned(), &event_type_patterns);
+        assert!(matches!(event_type, EventType::Unknown));
+        assert_eq!(unknown_diagnosis.unwrap().closest_pattern, "Flight");
+    }
+
+
+    #[test]
+    fn classify_uses_same_casing_as_classification()
+    {
+        let filepath: std::path::PathBuf = temp_path("patterns.toml");
+        std::fs::write(&filepath, "Hotel = '^Hotel: (?P<name>.+)$'\n").unwrap(); // case-sensitive, matched against original summary
+        let event_type_patterns: EventTypePatterns = EventTypePatterns::load(filepath.to_string_lossy().as_ref()).unwrap();
+        std::fs::remove_file(&filepath).unwrap();
+
+
+        assert_eq!(event_type_patterns.automata.len(), 11); // every pattern converted once at load
+        assert!(matches!(EventType::determine_event_type("Hotel: Maritim".to_owned(), &event_type_patterns), EventType::Hotel {name} if name == "Maritim"));
+        let unknown_diagnosis: UnknownDiagnosis = EventType::determine_event_type_verbose("Hotel: ".to_owned(), &event_type_patterns).1.unwrap(); // name missing
+        assert_eq!((unknown_diagnosis.closest_pattern, unknown_diagnosis.matched.as_str()), ("Hotel", "Hotel: ")); // uppercased summary would die at first "o"
+        let unknown_diagnosis: UnknownDiagnosis = EventType::determine_event_type_verbose("lh 100: fra-".to_owned(), &event_type_patterns).1.unwrap();
+        assert_eq!((unknown_diagnosis.closest_pattern, unknown_diagnosis.matched.as_str()), ("Flight", "LH 100: FRA-")); // other patterns uppercased
+    }
 }
\ No newline at end of file
@@ -0,0 +1,199 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::error::*;
+
+
+/// # Summary
+/// Compiled regex patterns to determine the event type of a summary. Built-in patterns can be overridden per event type by a TOML file mapping event type names to regex strings, so rosters of other formats can be supported without changing code. All patterns except "Hotel" are matched against the uppercased summary.
+#[derive(Clone, Debug)]
+pub struct EventTypePatterns
+{
+    pub automata: std::collections::HashMap<&'static str, regex_automata::dfa::dense::DFA<Vec<u32>>>, // event type name -> automaton of its pattern, to diagnose how far a summary matches, missing if pattern can not be converted
+    pub briefing: regex::Regex,
+    pub deadhead: regex::Regex, // needs capture groups "flight_iata", "departure_iata", "destination_iata"
+    pub flight: regex::Regex, // needs capture groups "flight_iata", "departure_iata", "destination_iata"
+    pub ground: regex::Regex, // needs capture groups "category", "description"
+    pub holiday: regex::Regex,
+    pub hotel: regex::Regex, // needs capture group "name", matched against original summary to keep hotel name as is
+    pub layover: regex::Regex,
+    pub off: regex::Regex,
+    pub pickup: regex::Regex,
+    pub reserve: regex::Regex, // needs capture group "description"
+    pub sickness: regex::Regex,
+}
+
+impl EventTypePatterns
+{
+    /// # Summary
+    /// Loads the patterns from the TOML file at `filepath`, for example `Flight = '^(?P<flight_iata>...)...$'`. Event types not in the file keep their built-in pattern. If the file does not exist, all built-in patterns are used. Every pattern is compiled and checked for the capture groups its event type needs. Additionally every pattern is converted to an automaton once, so diagnosing unknown summaries does not need to build them again.
+    ///
+    /// # Arguments
+    /// - `filepath`: path to TOML file mapping event type names to regex strings, names are case-insensitive
+    ///
+    /// # Returns
+    /// - compiled patterns or error
+    pub fn load(filepath: &str) -> Result<Self, LoadEventTypePatternsError>
+    {
+        let mut patterns: std::collections::HashMap<String, String> = Self::default_patterns().into_iter().map(|(k, v)| (k.to_owned(), v.to_owned())).collect(); // event type name -> pattern, built-in first
+        let event_type_patterns: Self; // compiled patterns
+
+
+        if std::fs::exists(filepath).unwrap_or(false) // if file exists: override built-in patterns
+        {
+            let patterns_custom: std::collections::HashMap<String, String> = toml::from_str(std::fs::read_to_string(filepath)?.as_str())?; // parse file
+            for (event_type_name, pattern) in patterns_custom
+            {
+                match Self::default_patterns().iter().find(|(k, _)| k.eq_ignore_ascii_case(&event_type_name)) // normalise name to built-in spelling
+                {
+                    Some((k, _)) => {patterns.insert((*k).to_owned(), pattern);},
+                    None => return Err(LoadEventTypePatternsError::UnknownEventType {event_type: event_type_name}), // typo would silently do nothing otherwise
+                }
+            }
+            log::info!("Loaded event type patterns from \"{filepath}\".");
+        }
+        else
+        {
+            log::debug!("Event type patterns file \"{filepath}\" does not exist. Using built-in patterns.");
+        }
+
+        event_type_patterns = Self
+        {
+            automata: Self::default_patterns().iter()
+                .filter_map(|(event_type_name, _)| regex_automata::dfa::dense::DFA::new(&patterns[*event_type_name]).ok().map(|dfa| (*event_type_name, dfa))) // regex crate can not match partially, so keep automata to walk manually, patterns with unicode word boundaries can not be converted
+                .collect(),
+            briefing: compile_pattern("Briefing", &patterns["Briefing"], &[])?,
+            deadhead: compile_pattern("Deadhead", &patterns["Deadhead"], &["flight_iata", "departure_iata", "destination_iata"])?,
+            flight: compile_pattern("Flight", &patterns["Flight"], &["flight_iata", "departure_iata", "destination_iata"])?,
+            ground: compile_pattern("Ground", &patterns["Ground"], &["category", "description"])?,
+            holiday: compile_pattern("Holiday", &patterns["Holiday"], &[])?,
+            hotel: compile_pattern("Hotel", &patterns["Hotel"], &["name"])?,
+            layover: compile_pattern("Layover", &patterns["Layover"], &[])?,
+            off: compile_pattern("Off", &patterns["Off"], &[])?,
+            pickup: compile_pattern("Pickup", &patterns["Pickup"], &[])?,
+            reserve: compile_pattern("Reserve", &patterns["Reserve"], &["description"])?,
+            sickness: compile_pattern("Sickness", &patterns["Sickness"], &[])?,
+        };
+
+        return Ok(event_type_patterns);
+    }
+
+
+    /// # Summary
+    /// Returns all patterns with the name of their event type, in the order they are tried.
+    ///
+    /// # Returns
+    /// - event type names and their patterns
+    pub fn all(&self) -> [(&'static str, &regex::Regex); 11]
+    {
+        return
+        [
+            ("Briefing", &self.briefing),
+            ("Deadhead", &self.deadhead),
+            ("Flight", &self.flight),
+            ("Ground", &self.ground),
+            ("Holiday", &self.holiday),
+            ("Hotel", &self.hotel),
+            ("Layover", &self.layover),
+            ("Off", &self.off),
+            ("Pickup", &self.pickup),
+            ("Reserve", &self.reserve),
+            ("Sickness", &self.sickness),
+        ];
+    }
+
+
+    /// # Summary
+    /// Returns the built-in patterns, matching the DLH myTime roster format.
+    ///
+    /// # Returns
+    /// - event type names and their built-in patterns
+    fn default_patterns() -> [(&'static str, &'static str); 11]
+    {
+        return
+        [
+            ("Briefing", r"^(\d{2}:\d{2} LT BRIEFING [A-Z]{3})$"),
+            ("Deadhead", r"^(DH (?P<flight_iata>[\dA-Z][A-Z] \d{1,4}): (?P<departure_iata>[A-Z]{3})-(?P<destination_iata>[A-Z]{3}))$"),
+            ("Flight", r"^((?P<flight_iata>[\dA-Z][A-Z] \d{1,4}): (?P<departure_iata>[A-Z]{3})-(?P<destination_iata>[A-Z]{3}))$"),
+            ("Ground", r"^((?P<category>GENERALEVENT|MANDATORY TRAINING|MEDICAL EVENT|OFFICE DAY|SIMULATOR) \((?P<description>.+)\))$"),
+            ("Holiday", r"^(ABSENCE \(.+\))$"),
+            ("Hotel", r"(?i)^(HTL \((?P<name>.+)\))$"), // case-insensitive instead of uppercasing summary to keep hotel name as is
+            ("Layover", r"^(LAYOVER( \[[A-Z]{3}\])?)$"),
+            ("Off", r"^(OFF DAY \(.+\))$"),
+            ("Pickup", r"^(\d{2}:\d{2} LT PICKUP [A-Z]{3})$"),
+            ("Reserve", r"^((RESERVE|STANDBY) \((?P<description>RB(_[0-9]+)?|RES|REP|SB(_[A-Z_]+)?)\))$"),
+            ("Sickness", r"^(SICKNESS \(K(O)?\))$"),
+        ];
+    }
+}
+
+
+/// # Summary
+/// Compiles a pattern and checks that it contains every capture group its event type needs.
+///
+/// # Arguments
+/// - `event_type`: name of the event type the pattern belongs to, for error messages
+/// - `pattern`: regex string
+/// - `capture_groups`: names of the capture groups the event type needs
+///
+/// # Returns
+/// - compiled pattern or error
+fn compile_pattern(event_type: &str, pattern: &str, capture_groups: &[&str]) -> Result<regex::Regex, LoadEventTypePatternsError>
+{
+    let regex: regex::Regex = regex::Regex::new(pattern).map_err(|e| LoadEventTypePatternsError::Regex {event_type: event_type.to_owned(), source: e})?; // compile
+
+
+    for capture_group in capture_groups
+    {
+        if !regex.capture_names().flatten().any(|s| s == *capture_group) // capture group missing: transformation would not find its data
+        {
+            return Err(LoadEventTypePatternsError::CaptureGroupMissing {event_type: event_type.to_owned(), capture_group: (*capture_group).to_owned()});
+        }
+    }
+
+    return Ok(regex);
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::event_type::*;
+    use crate::test_harness::*;
+
+
+    /// # Summary
+    /// Writes `content` to a temporary patterns file and loads it.
+    fn load_custom(content: &str) -> Result<EventTypePatterns, LoadEventTypePatternsError>
+    {
+        let filepath: std::path::PathBuf = temp_path("patterns.toml");
+        std::fs::write(&filepath, content).unwrap();
+        let event_type_patterns: Result<EventTypePatterns, LoadEventTypePatternsError> = EventTypePatterns::load(filepath.to_string_lossy().as_ref());
+        std::fs::remove_file(filepath).unwrap();
+        return event_type_patterns;
+    }
+
+
+    #[test]
+    fn custom_patterns_override_built_in()
+    {
+        let event_type_patterns: EventTypePatterns = load_custom("flight = '^(?P<flight_iata>[A-Z]{2}\\d{1,4}) (?P<departure_iata>[A-Z]{3})/(?P<destination_iata>[A-Z]{3})$'\nOff = '^FREI$'\n").unwrap(); // names case-insensitive
+
+
+        assert!(matches!(EventType::determine_event_type("LH100 FRA/MUC".to_owned(), &event_type_patterns), EventType::Flight {flight_iata, departure_iata, destination_iata} if flight_iata == "LH100" && departure_iata == "FRA" && destination_iata == "MUC"));
+        assert!(matches!(EventType::determine_event_type("LH 100: FRA-MUC".to_owned(), &event_type_patterns), EventType::Unknown)); // built-in replaced, not extended
+        assert!(matches!(EventType::determine_event_type("frei".to_owned(), &event_type_patterns), EventType::Off)); // matched against uppercased summary
+        assert!(matches!(EventType::determine_event_type("DH LH 100: FRA-MUC".to_owned(), &event_type_patterns), EventType::Deadhead {..})); // not in file: built-in kept
+        assert_eq!(event_type_patterns.flight.as_str(), load_custom("FLIGHT = '^(?P<flight_iata>[A-Z]{2}\\d{1,4}) (?P<departure_iata>[A-Z]{3})/(?P<destination_iata>[A-Z]{3})$'\n").unwrap().flight.as_str());
+    }
+
+
+    #[test]
+    fn invalid_custom_patterns_fail()
+    {
+        assert!(matches!(load_custom("Flights = '^X$'\n"), Err(LoadEventTypePatternsError::UnknownEventType {event_type}) if event_type == "Flights")); // typo
+        assert!(matches!(load_custom("Flight = '^(?P<flight_iata>X)$'\n"), Err(LoadEventTypePatternsError::CaptureGroupMissing {event_type, capture_group}) if event_type == "Flight" && capture_group == "departure_iata"));
+        assert!(matches!(load_custom("Off = '^(OFF$'\n"), Err(LoadEventTypePatternsError::Regex {event_type, ..}) if event_type == "Off"));
+        assert!(matches!(load_custom("Off = \n"), Err(LoadEventTypePatternsError::TomlDe(_))));
+        assert!(EventTypePatterns::load(temp_path("does_not_exist.toml").to_string_lossy().as_ref()).is_ok()); // missing file: built-in only
+    }
+}
\ No newline at end of file
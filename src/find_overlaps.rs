@@ -0,0 +1,118 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use icalendar::{Component, EventLike};
+use crate::dateperhapstime_to_datetime::*;
+
+
+/// # Summary
+/// Finds overlapping active events, which usually means the roster import produced a double booking. Archived events and all-day events are ignored. Each event is only compared to the event ending last among all events starting before it, so an event overlapping multiple earlier events is reported once, paired with the one of them ending last.
+///
+/// # Arguments
+/// - `input_calendar`: calendar to search for overlaps
+/// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+///
+/// # Returns
+/// - overlapping event pairs, earlier starting event first
+pub fn find_overlaps(input_calendar: &icalendar::Calendar, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> Vec<(EventSpan, EventSpan)>
+{
+    let mut event_spans: Vec<EventSpan> = Vec::new(); // active events with proper datetimes
+    let mut latest_ending: Option<&EventSpan> = None; // event ending last among all events compared so far
+    let mut overlaps: Vec<(EventSpan, EventSpan)> = Vec::new(); // overlapping event pairs
+
+
+    for calendar_event in input_calendar.iter().filter_map(|component| component.as_event())
+    {
+        match (calendar_event.get_start().map(dateperhapstime_to_datetime), calendar_event.get_end().map(dateperhapstime_to_datetime)) // only events with proper datetimes can overlap meaningfully
+        {
+            (Some(Ok(Some(start))), Some(Ok(Some(end)))) if *archive_end_dt < end => event_spans.push(EventSpan {uid: calendar_event.get_uid().unwrap_or_default().to_owned(), start, end}), // only active events
+            _ => continue,
+        }
+    }
+    event_spans.sort_by_key(|event_span| event_span.start); // sort by start so only the latest ending previous event needs to be compared
+
+    for event_span in event_spans.iter()
+    {
+        if let Some(s) = latest_ending
+        {
+            if event_span.start < s.end {overlaps.push((s.clone(), event_span.clone()));} // starts before previous one ended: overlap, touching is fine
+        }
+        if latest_ending.is_none_or(|s| s.end < event_span.end) {latest_ending = Some(event_span);} // remember event ending last
+    }
+
+    return overlaps;
+}
+
+
+/// # Summary
+/// Identifies an event and the time it spans.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct EventSpan
+{
+    pub uid: String, // event uid
+    pub start: chrono::DateTime<chrono::Utc>, // event start
+    pub end: chrono::DateTime<chrono::Utc>, // event end
+}
+
+impl std::fmt::Display for EventSpan
+{
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result
+    {
+        write!(f, "{} ({} to {})", self.uid, self.start.to_rfc3339(), self.end.to_rfc3339())
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn overlapping_active_events_are_found()
+    {
+        let mut input_calendar: icalendar::Calendar = icalendar::Calendar::new();
+        input_calendar.push(test_event("A", "2030-01-01T10:00:00Z", "2030-01-01T14:00:00Z", "FRA").uid("a").done());
+        input_calendar.push(test_event("B", "2030-01-01T11:00:00Z", "2030-01-01T12:00:00Z", "FRA").uid("b").done()); // inside a
+        input_calendar.push(test_event("C", "2030-01-01T13:00:00Z", "2030-01-01T15:00:00Z", "FRA").uid("c").done()); // overlaps a, not b
+        input_calendar.push(test_event("D", "2030-01-01T15:00:00Z", "2030-01-01T16:00:00Z", "FRA").uid("d").done()); // touches c: fine
+        input_calendar.push(test_event("E", "2030-01-01", "2030-01-02", "FRA").uid("e").done()); // all-day: ignored
+        input_calendar.push(test_event("F", "1999-01-01T10:00:00Z", "1999-01-01T12:00:00Z", "FRA").uid("f").done()); // archived: ignored
+        input_calendar.push(test_event("G", "1999-01-01T11:00:00Z", "1999-01-01T13:00:00Z", "FRA").uid("g").done());
+
+
+        let overlaps: Vec<(String, String)> = find_overlaps(&input_calendar, &archive_end_dt()).into_iter().map(|(event_span_1, event_span_2)| (event_span_1.uid, event_span_2.uid)).collect();
+
+        assert_eq!(overlaps, vec![("a".to_owned(), "b".to_owned()), ("a".to_owned(), "c".to_owned())]);
+    }
+
+
+    #[test]
+    fn overlap_is_reported_once()
+    {
+        let mut input_calendar: icalendar::Calendar = icalendar::Calendar::new();
+        input_calendar.push(test_event("A", "2030-01-01T10:00:00Z", "2030-01-01T12:00:00Z", "FRA").uid("a").done());
+        input_calendar.push(test_event("B", "2030-01-01T11:00:00Z", "2030-01-01T13:00:00Z", "FRA").uid("b").done());
+
+
+        let overlaps: Vec<(EventSpan, EventSpan)> = find_overlaps(&input_calendar, &archive_end_dt());
+
+        assert_eq!(overlaps.len(), 1); // 1 pair, so 1 warning
+        assert_eq!((overlaps[0].0.uid.as_str(), overlaps[0].1.uid.as_str()), ("a", "b"));
+    }
+
+
+    #[test]
+    fn event_overlapping_multiple_earlier_events_is_paired_with_latest_ending()
+    {
+        let mut input_calendar: icalendar::Calendar = icalendar::Calendar::new();
+        input_calendar.push(test_event("A", "2030-01-01T10:00:00Z", "2030-01-01T14:00:00Z", "FRA").uid("a").done());
+        input_calendar.push(test_event("B", "2030-01-01T11:00:00Z", "2030-01-01T15:00:00Z", "FRA").uid("b").done());
+        input_calendar.push(test_event("C", "2030-01-01T13:00:00Z", "2030-01-01T16:00:00Z", "FRA").uid("c").done()); // overlaps a and b
+
+
+        let overlaps: Vec<(String, String)> = find_overlaps(&input_calendar, &archive_end_dt()).into_iter().map(|(event_span_1, event_span_2)| (event_span_1.uid, event_span_2.uid)).collect();
+
+        assert_eq!(overlaps, vec![("a".to_owned(), "b".to_owned()), ("b".to_owned(), "c".to_owned())]); // c only reported against b
+    }
+}
\ No newline at end of file
@@ -0,0 +1,148 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use icalendar::{Component, EventLike};
+use crate::config::*;
+use crate::dateperhapstime_to_datetime::*;
+use crate::event_counts::*;
+use crate::event_type::*;
+use crate::event_type_patterns::*;
+
+
+/// # Summary
+/// Generates a calendar containing only a VFREEBUSY component, to share availability without details. Duties are busy, off days and holidays are free, everything else is omitted. Events of types in `EXCLUDED_EVENT_TYPES` are omitted as well. Overlapping and touching periods of the same type are merged. All-day events span from midnight to midnight utc. Events are not transformed, because only their times are output.
+///
+/// # Arguments
+/// - `input_calendar`: calendar to generate free/busy information from, usually loaded from the database
+/// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+/// - `config`: configuration, contains excluded event types
+/// - `event_type_patterns`: compiled patterns to determine event types
+///
+/// # Returns
+/// - calendar in ical format and the number of events per event type considered
+pub fn generate_freebusy(input_calendar: icalendar::Calendar, archive_end_dt: &chrono::DateTime<chrono::Utc>, config: &Config, event_type_patterns: &EventTypePatterns) -> (String, EventCounts)
+{
+    let mut busy: Vec<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> = Vec::new(); // busy periods
+    let mut calendar_events: Vec<(EventType, icalendar::Event)>; // input calendar events with their event types
+    let event_counts: EventCounts; // number of events per event type considered
+    let mut free: Vec<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> = Vec::new(); // free periods
+    let now_str: String = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string(); // conversion time in ical utc format
+    let mut s: String = String::new(); // output calendar
+
+
+    calendar_events = input_calendar.components.into_iter().filter_map(|calendar_component| match calendar_component // determine event types once, so they can be reused for excluding, counting, and periods
+    {
+        icalendar::CalendarComponent::Event(calendar_event) => Some((calendar_event.get_summary().map_or(EventType::NoSummary, |s| EventType::determine_event_type(s.to_owned(), event_type_patterns)), calendar_event)), // no summary: malformed, do not mix up with unknown summaries
+        _ => None, // not event: no period
+    }).collect();
+    if let Some(excluded_event_types) = &config.EXCLUDED_EVENT_TYPES // remove excluded event types completely, same as in transform_calendar
+    {
+        calendar_events.retain(|(event_type, _)| !event_type.is_excluded(excluded_event_types));
+    }
+    event_counts = EventCounts::count(&calendar_events, archive_end_dt); // count after excluding, same as in transform_calendar
+
+    for (event_type, calendar_event) in calendar_events
+    {
+        let (Some(start), Some(end)) = (calendar_event.get_start().and_then(dateperhapstime_to_utc), calendar_event.get_end().and_then(dateperhapstime_to_utc)) else {continue;}; // without proper start and end no period
+        match event_type
+        {
+            EventType::Briefing | EventType::Deadhead {..} | EventType::Flight {..} | EventType::Ground {..} | EventType::Layover | EventType::Pickup | EventType::Reserve {..} => busy.push((start, end)), // duty
+            EventType::Holiday | EventType::Off => free.push((start, end)), // free day
+            _ => {}, // private or unknown: do not reveal anything
+        }
+    }
+
+    s.push_str("BEGIN:VCALENDAR\r\nVERSION:2.0\r\nBEGIN:VFREEBUSY\r\n");
+    s.push_str(format!("UID:freebusy@{}\r\n", env!("CARGO_PKG_NAME")).as_str()); // stable uid, so clients replace previous free/busy information
+    s.push_str(format!("DTSTAMP:{now_str}\r\n").as_str());
+    if let (Some(start), Some(end)) = (busy.iter().chain(free.iter()).map(|(start, _)| start).min(), busy.iter().chain(free.iter()).map(|(_, end)| end).max()) // time range covered
+    {
+        s.push_str(format!("DTSTART:{}\r\nDTEND:{}\r\n", start.format("%Y%m%dT%H%M%SZ"), end.format("%Y%m%dT%H%M%SZ")).as_str());
+    }
+    for (fbtype, periods) in [("BUSY", busy), ("FREE", free)]
+    {
+        for (start, end) in merge_periods(periods)
+        {
+            s.push_str(format!("FREEBUSY;FBTYPE={fbtype}:{}/{}\r\n", start.format("%Y%m%dT%H%M%SZ"), end.format("%Y%m%dT%H%M%SZ")).as_str());
+        }
+    }
+    s.push_str("END:VFREEBUSY\r\nEND:VCALENDAR\r\n");
+
+    return (s, event_counts);
+}
+
+
+/// # Summary
+/// Converts a icalendar::DatePerhapsTime to a UTC datetime. Dates without time become midnight utc.
+///
+/// # Arguments
+/// - `dt`: date or perhaps datetime to convert
+///
+/// # Returns
+/// - UTC datetime or None if conversion failed
+fn dateperhapstime_to_utc(dt: icalendar::DatePerhapsTime) -> Option<chrono::DateTime<chrono::Utc>>
+{
+    match dt
+    {
+        icalendar::DatePerhapsTime::Date(d) => return Some(d.and_time(chrono::NaiveTime::MIN).and_utc()), // all-day: from midnight
+        _ => return dateperhapstime_to_datetime(dt).ok().flatten(),
+    }
+}
+
+
+/// # Summary
+/// Sorts periods and merges overlapping or touching ones.
+///
+/// # Arguments
+/// - `periods`: periods to merge
+///
+/// # Returns
+/// - sorted, non-overlapping periods
+fn merge_periods(mut periods: Vec<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>) -> Vec<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)>
+{
+    let mut periods_merged: Vec<(chrono::DateTime<chrono::Utc>, chrono::DateTime<chrono::Utc>)> = Vec::new(); // merged periods
+
+
+    periods.sort();
+    for (start, end) in periods
+    {
+        match periods_merged.last_mut()
+        {
+            Some(last) if start <= last.1 => last.1 = last.1.max(end), // overlaps or touches previous: extend
+            _ => periods_merged.push((start, end)),
+        }
+    }
+
+    return periods_merged;
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn duties_are_busy_and_days_off_free()
+    {
+        let mut input_calendar: icalendar::Calendar = icalendar::Calendar::new();
+        input_calendar.push(test_event("06:15 LT Briefing FRA", "2030-01-01T09:00:00Z", "2030-01-01T10:00:00Z", "FRA").uid("briefing").done());
+        input_calendar.push(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA").uid("flight").done()); // touches briefing
+        input_calendar.push(test_event("OFF DAY (X)", "2030-01-02", "2030-01-03", "FRA").uid("off").done());
+        input_calendar.push(test_event("SICKNESS (KO)", "2030-01-03", "2030-01-04", "FRA").uid("sickness").done());
+        input_calendar.push(test_event("SOMETHING NEW", "2030-01-04T10:00:00Z", "2030-01-04T11:00:00Z", "FRA").uid("unknown").done());
+        let freebusy = |config: &Config| generate_freebusy(input_calendar.clone(), &archive_end_dt(), config, &patterns_default());
+
+
+        let (output_calendar, event_counts) = freebusy(&Config::default());
+        assert_eq!(output_calendar.lines().filter(|line| line.starts_with("FREEBUSY")).collect::<Vec<&str>>(), vec!["FREEBUSY;FBTYPE=BUSY:20300101T090000Z/20300101T110000Z", "FREEBUSY;FBTYPE=FREE:20300102T000000Z/20300103T000000Z"]); // merged, private and unknown omitted
+        assert!(output_calendar.contains("DTSTART:20300101T090000Z\r\nDTEND:20300103T000000Z\r\n"));
+        assert!(!output_calendar.contains("SUMMARY")); // no details
+        assert_eq!(event_counts.total, 5);
+
+        let (output_calendar, event_counts) = freebusy(&Config {EXCLUDED_EVENT_TYPES: Some(vec!["off".to_owned(), "Flight".to_owned()]), ..Config::default()});
+        assert_eq!(output_calendar.lines().filter(|line| line.starts_with("FREEBUSY")).collect::<Vec<&str>>(), vec!["FREEBUSY;FBTYPE=BUSY:20300101T090000Z/20300101T100000Z"]); // excluded neither busy nor free
+        assert_eq!(event_counts.total, 3);
+        assert_eq!(event_counts.per_event_type.get("Off"), None);
+    }
+}
\ No newline at end of file
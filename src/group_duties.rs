@@ -0,0 +1,160 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use icalendar::{Component, EventLike};
+use crate::dateperhapstime_to_datetime::*;
+use crate::event_type::*;
+
+
+/// # Summary
+/// Groups consecutive briefing and flight events into duties. A duty starts at a briefing and ends at the end of the last flight or deadhead following it. Flights only belong to the duty if the gap to the previous briefing or flight is at most `max_gap`. Briefings without any following flight do not result in a duty. All-day events are ignored.
+///
+/// # Arguments
+/// - `calendar_events`: calendar events with their event types, sorted by start
+/// - `max_gap`: maximum gap between the end of the previous event and the start of the next flight to still be considered the same duty
+///
+/// # Returns
+/// - one event with summary "Duty" per duty found
+pub fn group_duties(calendar_events: &[(EventType, icalendar::Event)], max_gap: chrono::Duration) -> Vec<icalendar::Event>
+{
+    let mut duty: Option<Duty> = None; // duty currently being grouped
+    let mut duty_events: Vec<icalendar::Event> = Vec::new(); // grouped duties
+
+
+    for (event_type, calendar_event) in calendar_events
+    {
+        let start: chrono::DateTime<chrono::Utc>;
+        let end: chrono::DateTime<chrono::Utc>;
+
+        match (calendar_event.get_start().map(dateperhapstime_to_datetime), calendar_event.get_end().map(dateperhapstime_to_datetime)) // only events with proper datetimes can be part of a duty
+        {
+            (Some(Ok(Some(o1))), Some(Ok(Some(o2)))) => (start, end) = (o1, o2),
+            _ => continue,
+        }
+
+        match event_type
+        {
+            EventType::Briefing => // new duty starts
+            {
+                if let Some(s) = duty.take() {duty_events.extend(s.into_event());} // finish previous duty
+                duty = Some(Duty {uid: calendar_event.get_uid().unwrap_or_default().to_owned(), start, end: None, last_end: end});
+            },
+            EventType::Deadhead {..} | EventType::Flight {..} =>
+            {
+                match duty.take()
+                {
+                    Some(mut s) if start - s.last_end <= max_gap => // flight close enough to previous event: extend duty
+                    {
+                        s.end = Some(end);
+                        s.last_end = end;
+                        duty = Some(s);
+                    },
+                    Some(s) => duty_events.extend(s.into_event()), // gap too large: finish duty, flight without briefing starts no new duty
+                    None => {}, // flight without briefing: no duty
+                }
+            },
+            _ => {}, // other events neither extend nor finish a duty
+        }
+    }
+    if let Some(s) = duty.take() {duty_events.extend(s.into_event());} // finish last duty
+
+    return duty_events;
+}
+
+
+/// # Summary
+/// Duty currently being grouped.
+struct Duty
+{
+    uid: String, // uid of briefing starting the duty
+    start: chrono::DateTime<chrono::Utc>, // start of briefing
+    end: Option<chrono::DateTime<chrono::Utc>>, // end of last flight, None if no flight yet
+    last_end: chrono::DateTime<chrono::Utc>, // end of last event belonging to duty, to check gap to next flight
+}
+
+impl Duty
+{
+    /// # Summary
+    /// Converts the duty to a calendar event spanning from briefing start to last flight end. If the duty contains no flight, returns None.
+    ///
+    /// # Returns
+    /// - duty calendar event or None
+    fn into_event(self) -> Option<icalendar::Event>
+    {
+        let mut calendar_event: icalendar::Event = icalendar::Event::new();
+
+
+        calendar_event.uid(format!("{}-duty", self.uid).as_str());
+        calendar_event.summary("Duty");
+        calendar_event.starts(self.start);
+        calendar_event.ends(self.end?); // no flight: no duty
+
+        return Some(calendar_event);
+    }
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    /// # Summary
+    /// Creates a flight from FRA to MUC with the given times.
+    fn flight(start_dt: &str, end_dt: &str) -> (EventType, icalendar::Event)
+    {
+        return (EventType::Flight {flight_iata: "LH100".to_owned(), departure_iata: "FRA".to_owned(), destination_iata: "MUC".to_owned()}, test_event("LH 100: FRA-MUC", start_dt, end_dt, "FRA"));
+    }
+
+
+    #[test]
+    fn briefing_and_flights_are_grouped()
+    {
+        let calendar_events: Vec<(EventType, icalendar::Event)> = vec!
+        [
+            (EventType::Briefing, test_event("06:15 LT BRIEFING FRA", "2030-01-01T06:15:00Z", "2030-01-01T07:15:00Z", "FRA").uid("briefing").done()),
+            flight("2030-01-01T08:00:00Z", "2030-01-01T09:00:00Z"),
+            (EventType::Layover, test_event("LAYOVER", "2030-01-01T09:00:00Z", "2030-01-01T10:00:00Z", "MUC")), // neither extends nor finishes duty
+            flight("2030-01-01T10:30:00Z", "2030-01-01T11:30:00Z"),
+        ];
+
+
+        let duty_events: Vec<icalendar::Event> = group_duties(&calendar_events, chrono::Duration::hours(3));
+
+        assert_eq!(duty_events.len(), 1);
+        assert_eq!(duty_events[0].get_uid(), Some("briefing-duty"));
+        assert_eq!(duty_events[0].get_summary(), Some("Duty"));
+        assert_eq!(duty_events[0].get_start().map(dateperhapstime_to_datetime).unwrap().unwrap(), Some(chrono::DateTime::parse_from_rfc3339("2030-01-01T06:15:00Z").unwrap().to_utc()));
+        assert_eq!(duty_events[0].get_end().map(dateperhapstime_to_datetime).unwrap().unwrap(), Some(chrono::DateTime::parse_from_rfc3339("2030-01-01T11:30:00Z").unwrap().to_utc()));
+    }
+
+
+    #[test]
+    fn gap_too_large_finishes_duty()
+    {
+        let calendar_events: Vec<(EventType, icalendar::Event)> = vec!
+        [
+            (EventType::Briefing, test_event("06:15 LT BRIEFING FRA", "2030-01-01T06:15:00Z", "2030-01-01T07:15:00Z", "FRA")),
+            flight("2030-01-01T08:00:00Z", "2030-01-01T09:00:00Z"),
+            flight("2030-01-01T13:00:00Z", "2030-01-01T14:00:00Z"), // 4 h gap: not part of duty
+        ];
+
+
+        let duty_events: Vec<icalendar::Event> = group_duties(&calendar_events, chrono::Duration::hours(3));
+
+        assert_eq!(duty_events.len(), 1);
+        assert_eq!(duty_events[0].get_end().map(dateperhapstime_to_datetime).unwrap().unwrap(), Some(chrono::DateTime::parse_from_rfc3339("2030-01-01T09:00:00Z").unwrap().to_utc()));
+    }
+
+
+    #[test]
+    fn briefing_or_flight_alone_is_no_duty()
+    {
+        let briefing_only: Vec<(EventType, icalendar::Event)> = vec![(EventType::Briefing, test_event("06:15 LT BRIEFING FRA", "2030-01-01T06:15:00Z", "2030-01-01T07:15:00Z", "FRA"))];
+        let flight_only: Vec<(EventType, icalendar::Event)> = vec![flight("2030-01-01T08:00:00Z", "2030-01-01T09:00:00Z")];
+
+
+        assert!(group_duties(&briefing_only, chrono::Duration::hours(3)).is_empty());
+        assert!(group_duties(&flight_only, chrono::Duration::hours(3)).is_empty());
+    }
+}
\ No newline at end of file
@@ -5,7 +5,7 @@ use crate::error::*;
 
 
 /// # Summary
-/// Loads the whole calendar from the database at `db` and returns the calendar as icalendar::Calendar.
+/// Loads the whole calendar from the database at `db` and returns the calendar as icalendar::Calendar. Events with mixed date and datetime start and end are normalized to all-day events.
 ///
 /// # Arguments
 /// - `db`: database connection pool
@@ -24,19 +24,32 @@ pub fn load_calendar(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> R
     {
         let mut event = icalendar::Event::new();
         event.uid(row.get::<&str, std::string::String>("uid")?.as_str()); // set uid
-        event.summary(row.get::<&str, std::string::String>("summary")?.as_str()); // set summary
-        match row.get::<&str, chrono::DateTime<chrono::Utc>>("start_dt") // try to load start as datetime
+        if let Some(s) = row.get::<&str, Option<std::string::String>>("summary")? {event.summary(s.as_str());} // set summary if exists, missing summary is handled during transformation
+        match (row.get::<&str, chrono::DateTime<chrono::Utc>>("start_dt").ok(), row.get::<&str, chrono::DateTime<chrono::Utc>>("end_dt").ok()) // try to load start and end as datetime
         {
-            Ok(o) => event.starts(o),
-            Err(_) => event.starts(row.get::<&str, chrono::NaiveDate>("start_dt")?), // if not possible: try to load as date
-        };
-        match row.get::<&str, chrono::DateTime<chrono::Utc>>("end_dt") // try to load end as datetime
-        {
-            Ok(o) => event.ends(o),
-            Err(_) => event.ends(row.get::<&str, chrono::NaiveDate>("end_dt")?), // if not possible: try to load as date
-        };
+            (Some(start), Some(end)) => {event.starts(start).ends(end);}, // both datetime: consistent
+            (None, None) => {event.starts(row.get::<&str, chrono::NaiveDate>("start_dt")?).ends(row.get::<&str, chrono::NaiveDate>("end_dt")?);}, // if not possible: try to load both as date
+            (start, end) => // one datetime, one date: some clients reject this, normalize both to date, meaning all-day
+            {
+                let start_date: chrono::NaiveDate = match start
+                {
+                    Some(o) => o.date_naive(),
+                    None => row.get::<&str, chrono::NaiveDate>("start_dt")?,
+                };
+                let mut end_date: chrono::NaiveDate = match end
+                {
+                    Some(o) if o.time() == chrono::NaiveTime::MIN => o.date_naive(), // ends at midnight: date end is exclusive, so same date
+                    Some(o) => o.date_naive() + chrono::Days::new(1), // date end is exclusive, so next day to include last day
+                    None => row.get::<&str, chrono::NaiveDate>("end_dt")?,
+                };
+                if end_date <= start_date {end_date = start_date + chrono::Days::new(1);} // all-day events last at least 1 day
+                log::warn!("Event {} has mixed date and datetime start and end. Normalized to all-day event from {start_date} to {end_date}.", row.get::<&str, std::string::String>("uid")?);
+                event.starts(start_date).ends(end_date);
+            },
+        }
         event.location(row.get::<&str, std::string::String>("location")?.as_str());
         event.description(row.get::<&str, std::string::String>("description")?.as_str());
+        if let Some(s) = row.get::<&str, Option<std::string::String>>("rrule")? {event.add_property("RRULE", s.as_str());} // set recurrence rule if exists, transformation leaves it untouched
 
         Ok(event)
     })?;
@@ -46,4 +59,60 @@ pub fn load_calendar(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> R
     }
 
     return Ok(calendar);
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::config::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn pipeline_runs_offline()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        seed_event(&db, "flight", Some("LH 100: FRA-MUC"), "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA");
+        seed_event(&db, "off", Some("OFF DAY (X)"), "2030-01-02", "2030-01-03", "FRA");
+
+
+        let (output_calendar, event_counts) = run_pipeline(&db, &Config::default());
+        let flight: Vec<String> = event_lines(&output_calendar, "flight");
+        let off: Vec<String> = event_lines(&output_calendar, "off");
+
+        assert_eq!(event_counts.total, 2);
+        assert_eq!(property_values(&flight, "SUMMARY"), vec!["LH100: EDDF ✈ EDDM".to_owned()]);
+        assert_eq!(property_values(&flight, "ACTION"), vec!["DISPLAY".to_owned()]);
+        assert_eq!(property_values(&flight, "TRIGGER"), vec!["-PT1800S".to_owned()]); // -30 min, converted to minutes only when saving
+        assert_eq!(property_values(&off, "SUMMARY"), vec!["Off".to_owned()]);
+        assert!(!off.contains(&"BEGIN:VALARM".to_owned())); // off days have no alarm
+    }
+
+
+    #[test]
+    fn mixed_date_and_datetime_are_normalized_to_all_day()
+    {
+        let db = test_db();
+        seed_event(&db, "datetime_date", Some("X"), "2030-01-01T10:00:00Z", "2030-01-03", "FRA");
+        seed_event(&db, "date_midnight", Some("X"), "2030-01-01", "2030-01-02T00:00:00Z", "FRA");
+        seed_event(&db, "date_datetime", Some("X"), "2030-01-01", "2030-01-01T05:00:00Z", "FRA");
+        seed_event(&db, "datetime", Some("X"), "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA");
+
+
+        let calendar: icalendar::Calendar = load_calendar(&db).unwrap();
+        let dates = |uid: &str| -> (Option<icalendar::DatePerhapsTime>, Option<icalendar::DatePerhapsTime>)
+        {
+            let calendar_event: &icalendar::Event = calendar.iter().filter_map(|component| component.as_event()).find(|calendar_event| calendar_event.get_uid() == Some(uid)).unwrap();
+            return (calendar_event.get_start(), calendar_event.get_end());
+        };
+        let date = |s: &str| Some(icalendar::DatePerhapsTime::Date(chrono::NaiveDate::parse_from_str(s, "%Y-%m-%d").unwrap()));
+
+        assert_eq!(dates("datetime_date"), (date("2030-01-01"), date("2030-01-03")));
+        assert_eq!(dates("date_midnight"), (date("2030-01-01"), date("2030-01-02"))); // midnight is exclusive end already
+        assert_eq!(dates("date_datetime"), (date("2030-01-01"), date("2030-01-02"))); // at least 1 day
+        assert!(matches!(dates("datetime"), (Some(icalendar::DatePerhapsTime::DateTime(_)), Some(icalendar::DatePerhapsTime::DateTime(_))))); // consistent: unchanged
+    }
 }
\ No newline at end of file
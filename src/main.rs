@@ -1,23 +1,54 @@
 // Copyright (c) 2024 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+mod airport_resolver;
 mod api_response;
+mod build_http_client;
+mod check_db;
+mod classify;
+mod cli_action;
+use cli_action::*;
 mod config;
 use config::*;
+mod config_sources;
+use config_sources::*;
 mod connect_to_db;
+mod dateperhapstime_to_datetime;
 mod dateperhapstime_to_string;
+mod delete_old_logs;
+mod download_calendar;
 mod error;
+mod event_counts;
 mod event_type;
+mod event_type_patterns;
+mod find_overlaps;
+mod generate_freebusy;
+mod group_duties;
 mod is_archived;
 mod load_calendar;
 mod main_inner;
 use main_inner::*;
+mod parse_tzid;
+mod reset_db;
+mod restore_events;
+mod run_iteration;
+mod spawn_airport_refresh;
+#[cfg(test)]
+mod test_harness;
+mod transform_calendar;
 mod transform_calendar_event;
 mod update_calendar;
 mod update_db;
+mod write_file_atomic;
 
 
 fn main() -> std::process::ExitCode
 {
+    const CONFIG_FILEPATH_DEFAULT: &str = "./config/.env"; // default config file path, used if CONFIG_FILEPATHS is unset
+    const CONFIG_FILEPATHS_ENV: &str = "CONFIG_FILEPATHS"; // env var containing comma separated config file paths, base first, overrides after
+    const EVENT_TYPE_PATTERNS_FILEPATH: &str = "./config/patterns.toml"; // optional file overriding built-in event type patterns
+    let cli_action: CliAction; // action to execute, determined by command line arguments
     let config: Config; // config, contains settings
+    let event_type_patterns: event_type_patterns::EventTypePatterns; // compiled event type patterns
+    let mut config_filepaths: Vec<String>; // config file paths, base first, overrides after
 
 
     std::panic::set_hook(Box::new(|panic_info: &std::panic::PanicHookInfo| // override panic behaviour
@@ -26,14 +57,51 @@ fn main() -> std::process::ExitCode
         log::error!("{}", std::backtrace::Backtrace::capture()); // log backtrace
     }));
 
+    match CliAction::parse(&std::env::args().skip(1).collect::<Vec<String>>()) // parse command line arguments, before loading config so help and version work without config
+    {
+        Ok(CliAction::Classify(summary)) => // test event type patterns without running converter
+        {
+            match event_type_patterns::EventTypePatterns::load(EVENT_TYPE_PATTERNS_FILEPATH) // classify with the same patterns the converter would use
+            {
+                Ok(o) => event_type_patterns = o,
+                Err(e) =>
+                {
+                    eprintln!("{e}");
+                    return std::process::ExitCode::from(error::Error::EXIT_CODE_CONFIG);
+                },
+            }
+            println!("{}", classify::classify(summary, &event_type_patterns));
+            return std::process::ExitCode::SUCCESS;
+        },
+        Ok(CliAction::Help) =>
+        {
+            println!("{}", CliAction::help());
+            return std::process::ExitCode::SUCCESS;
+        },
+        Ok(o @ (CliAction::CheckDb | CliAction::ResetDb | CliAction::Run)) => cli_action = o, // need config, continue normally
+        Ok(CliAction::Version) =>
+        {
+            println!("{}", env!("CARGO_PKG_VERSION"));
+            return std::process::ExitCode::SUCCESS;
+        },
+        Err(e) =>
+        {
+            eprintln!("{e}\n\n{}", CliAction::help());
+            return std::process::ExitCode::from(error::Error::EXIT_CODE_CLI);
+        },
+    }
+
+    config_filepaths = std::env::var(CONFIG_FILEPATHS_ENV).unwrap_or_default() // load config file paths from env
+        .split(',')
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .collect();
+    if config_filepaths.is_empty() {config_filepaths = vec![CONFIG_FILEPATH_DEFAULT.to_owned()];} // if unset or empty: single default file
+
     match load_config::load_config // load config
     (
-        vec!
-        [
-            load_config::Source::Env,
-            load_config::Source::File(load_config::SourceFile::Toml("./config/.env".to_string())),
-        ],
-        Some(load_config::SourceFile::Toml("./config/.env".to_string())),
+        config_sources(&config_filepaths),
+        Some(load_config::SourceFile::Toml(config_filepaths[0].to_owned())), // if no config found: create default at base file path only, overrides are never created
     )
     {
         Ok(o) => config = o, // loaded config successfully
@@ -45,7 +113,7 @@ fn main() -> std::process::ExitCode
                 load_config::Error::CreatedDefaultFile {..} => log::info!("{e}"), // if default file created successfully: log as info
                 _ => log::error!("{e}"),
             }
-            return std::process::ExitCode::FAILURE;
+            return std::process::ExitCode::from(error::Error::EXIT_CODE_CONFIG); // also if default file created, config has to be filled in before running
         }
     }
 
@@ -59,9 +127,18 @@ fn main() -> std::process::ExitCode
     }
 
     log::debug!("Loaded {config:?}."); // log loaded config
+    match event_type_patterns::EventTypePatterns::load(EVENT_TYPE_PATTERNS_FILEPATH) // compile once at startup, invalid patterns fail early instead of every iteration
+    {
+        Ok(o) => event_type_patterns = o,
+        Err(e) =>
+        {
+            log::error!("{e}");
+            return std::process::ExitCode::from(error::Error::EXIT_CODE_CONFIG);
+        },
+    }
 
 
-    match std::panic::catch_unwind(|| main_inner(config)) // execute main_inner, catch panic
+    match std::panic::catch_unwind(|| main_inner(config, cli_action, event_type_patterns)) // execute main_inner, catch panic
     {
         Ok(result) => // no panic
         {
@@ -71,7 +148,7 @@ fn main() -> std::process::ExitCode
                 Err(e) => // program failed in a controlled manner
                 {
                     log::error!("{e}"); // log error
-                    return std::process::ExitCode::FAILURE;
+                    return std::process::ExitCode::from(e.exit_code()); // exit code by failure class
                 }
             }
         }
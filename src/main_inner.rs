@@ -1,49 +1,217 @@
 // Copyright (c) 2024 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::build_http_client::*;
+use crate::check_db::*;
+use crate::cli_action::*;
 use crate::config::*;
 use crate::connect_to_db::*;
+use crate::delete_old_logs::*;
 use crate::error::*;
+use crate::event_type::*;
+use crate::event_type_patterns::*;
+use crate::reset_db::*;
+use crate::run_iteration::*;
+use crate::spawn_airport_refresh::*;
 use crate::update_calendar::*;
 use crate::update_db::*;
 
 
-pub fn main_inner(config: Config) -> Result<(), Error>
+pub fn main_inner(config: Config, cli_action: CliAction, event_type_patterns: EventTypePatterns) -> Result<(), Error>
 {
     const AIRPORT_DATA_URL: &str = "https://ourairports.com/data/airports.csv"; // airport data online
     const COUNTRY_DATA_URL: &str = "https://ourairports.com/data/countries.csv"; // country data online
     const DB_URL: &str = "./db/db.sqlite"; // database url, usually local filepath
     const DB_MIGRATIONS_DIR: include_dir::Dir = include_dir::include_dir!("./db_migrations/"); // database migrations directory
-    const DB_MIGRATIONS_VERSION: usize = 1;
-    const HTTP_TIMEOUT: u64 = 10; // connection timeout
+    const DB_MIGRATIONS_VERSION: usize = 4;
+    const LOG_DIRPATH: &str = "./log/"; // directory log files are written to, must match setup_logging in main
     let db: r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>; // database connection pool
     let http_client: reqwest::blocking::Client; // http client
+    let mut airport_refresh_handle: Option<std::thread::JoinHandle<()>> = None; // thread refreshing airport and country data, only if AIRPORT_REFRESH_INTERVAL
+    let mut iteration_handle: Option<std::thread::JoinHandle<()>> = None; // thread of previous iteration, only if ITERATION_TIMEOUT
 
 
-    http_client = reqwest::blocking::Client::builder()  // create http client
-        .danger_accept_invalid_certs(true) // accept invalid certificates from ourairports.com
-        .timeout(Some(std::time::Duration::from_secs(HTTP_TIMEOUT)))
-        .build()?;
-    db = connect_to_db(DB_URL, &DB_MIGRATIONS_DIR, DB_MIGRATIONS_VERSION)?; // connect to database
-    if let Err(e) = update_airports(&http_client, AIRPORT_DATA_URL, &db) // download airport data, parse csv, update database
+    validate_config(&config)?; // fail early instead of every iteration
+
+    if let Some(log_retention_days) = config.LOG_RETENTION_DAYS // if LOG_RETENTION_DAYS unset default to keeping log files forever
+    {
+        match delete_old_logs(LOG_DIRPATH, log_retention_days)
+        {
+            Ok(o) => log::info!("Deleted {o} log files older than {log_retention_days} days."),
+            Err(e) => log::warn!("Deleting old log files failed with: {e}\nContinuing without cleanup."),
+        }
+    }
+
+    db = connect_to_db(DB_URL, &DB_MIGRATIONS_DIR, DB_MIGRATIONS_VERSION, std::time::Duration::from_millis(config.DB_BUSY_TIMEOUT.unwrap_or(5000)))?; // connect to database, if DB_BUSY_TIMEOUT unset default to 5 s
+    if cli_action == CliAction::CheckDb // only check database and exit
+    {
+        let db_check: DbCheck = check_db(&db)?;
+        if !db_check.is_plausible() {return Err(Error::DbImplausible(db_check.to_string()));} // counts too low: fail so scripts notice
+        log::info!("Airport database is plausible: {db_check}");
+        return Ok(());
+    }
+    if cli_action == CliAction::ResetDb // only reset database and exit
+    {
+        let events_deleted: usize = reset_db(&db)?;
+        log::info!("Reset event database. Deleted {events_deleted} events, next run pulls whole calendar again.");
+        return Ok(());
+    }
+    http_client = build_http_client(&config)?; // create http client
+    if let Err(e) = update_airports(&http_client, AIRPORT_DATA_URL, &db, config.MIN_PARSE_SUCCESS_RATIO.unwrap_or(0.9)) // download airport data, parse csv, update database
     {
         log::warn!("Updating airport database failed with: {e}\nContinuing with potentially outdated data.");
     }
-    if let Err(e) = update_countries(&http_client, COUNTRY_DATA_URL, &db) // download country data, parse csv, update database
+    if let Err(e) = update_countries(&http_client, COUNTRY_DATA_URL, &db, config.MIN_PARSE_SUCCESS_RATIO.unwrap_or(0.9)) // download country data, parse csv, update database
     {
         log::warn!("Updating country database failed with: {e}\nContinuing with potentially outdated data.");
     }
+    if let Some(airport_refresh_interval) = config.AIRPORT_REFRESH_INTERVAL // if AIRPORT_REFRESH_INTERVAL unset default to only updating at startup
+    {
+        airport_refresh_handle = Some(spawn_airport_refresh(http_client.clone(), AIRPORT_DATA_URL.to_owned(), COUNTRY_DATA_URL.to_owned(), db.clone(), std::time::Duration::from_secs(airport_refresh_interval), config.MIN_PARSE_SUCCESS_RATIO.unwrap_or(0.9))); // refresh in background, so calendar updates are not delayed by large downloads
+    }
 
 
     loop
     {
         log::info!("--------------------------------------------------");
-        let archive_end_dt: chrono::DateTime<chrono::Utc> = chrono::Utc::now() + config.ARCHIVE_END_RELATIVE; // when archive ends in this iteration, read clock once to have clear reference point for archiving per iteration
+        let archive_end_dt: chrono::DateTime<chrono::Utc> = determine_archive_end_dt(&config, chrono::Utc::now()); // when archive ends in this iteration, read clock once to have clear reference point for archiving per iteration
         log::debug!("Archive end: {}", archive_end_dt.to_rfc3339());
+        if airport_refresh_handle.take_if(|handle| handle.is_finished()).is_some() // refresh thread only ends if it panicked, panic hook logged details, take to log only once
+        {
+            log::error!("Refreshing airport and country data stopped. Continuing with data as of last refresh.");
+        }
 
-        if let Err(e) = update_calendar(&http_client, config.INPUT_CALENDAR_URL.as_str(), config.OUTPUT_CALENDAR_FILEPATH.as_str(), &db, &archive_end_dt) // update calendar iteration
+        match config.ITERATION_TIMEOUT // if ITERATION_TIMEOUT unset default to waiting forever
         {
-            log::error!("Updating calendar failed with: {e}"); // log error
+            Some(iteration_timeout) => // run iteration on separate thread so waiting for it can be stopped, hanging thread can not be killed but does not block the daemon anymore
+            {
+                let (http_client, config, db, event_type_patterns) = (http_client.clone(), config.clone(), db.clone(), event_type_patterns.clone()); // thread needs its own handles, clients, pools, and regexes are cheap to clone
+                match run_iteration(move || update_calendar(&http_client, &config, &db, &archive_end_dt, &event_type_patterns), std::time::Duration::from_secs(iteration_timeout), &mut iteration_handle) // update calendar iteration
+                {
+                    IterationOutcome::Finished(Ok(_)) => {},
+                    IterationOutcome::Finished(Err(e)) => log::error!("Updating calendar failed with: {e}"), // log error
+                    IterationOutcome::Panicked => log::error!("Updating calendar aborted without result."), // thread panicked, panic hook logged details
+                    IterationOutcome::Skipped => log::warn!("Previous iteration is still running. Skipped this iteration to not run 2 at the same time."),
+                    IterationOutcome::TimedOut => log::error!("Updating calendar did not finish within {iteration_timeout} s. Continuing with next iteration."),
+                }
+            },
+            None =>
+            {
+                if let Err(e) = update_calendar(&http_client, &config, &db, &archive_end_dt, &event_type_patterns) // update calendar iteration
+                {
+                    log::error!("Updating calendar failed with: {e}"); // log error
+                }
+            },
         }
 
-        std::thread::sleep(std::time::Duration::from_secs(config.SLEEP_INTERVAL)); // sleep between updates
+        std::thread::sleep(sleep_duration(config.SLEEP_INTERVAL, config.SLEEP_JITTER.unwrap_or(0))); // sleep between updates, if SLEEP_JITTER unset default to 0
+    }
+}
+
+
+/// # Summary
+/// Checks config settings that can only be invalid in combination or against known names, so the daemon fails at startup instead of every iteration.
+///
+/// # Arguments
+/// - `config`: configuration to check
+///
+/// # Returns
+/// - nothing or error
+fn validate_config(config: &Config) -> Result<(), Error>
+{
+    if !config.DISABLE_ALARMS.unwrap_or(false) && config.ALARM_ACTION == Some(AlarmAction::Email) && config.ALARM_EMAIL.as_deref().unwrap_or_default().is_empty() // email alarms without recipient are invalid
+    {
+        return Err(Error::AlarmEmailMissing);
+    }
+    if let Some(s) = config.EXCLUDED_EVENT_TYPES.iter().flatten().find(|excluded_event_type| !EventType::names().iter().any(|name| name.eq_ignore_ascii_case(excluded_event_type))) // unknown names would silently exclude nothing
+    {
+        return Err(Error::ExcludedEventTypeUnknown(s.to_owned()));
+    }
+
+    return Ok(());
+}
+
+
+/// # Summary
+/// Determines when the archive ends. `ARCHIVE_END_ABSOLUTE` overrides `ARCHIVE_END_RELATIVE`, so reprocessing with the same config yields the same archive end.
+///
+/// # Arguments
+/// - `config`: configuration, contains archive end settings
+/// - `now`: current datetime, `ARCHIVE_END_RELATIVE` is relative to it
+///
+/// # Returns
+/// - datetime when archive ends
+fn determine_archive_end_dt(config: &Config, now: chrono::DateTime<chrono::Utc>) -> chrono::DateTime<chrono::Utc>
+{
+    return config.ARCHIVE_END_ABSOLUTE.unwrap_or(now + config.ARCHIVE_END_RELATIVE); // if ARCHIVE_END_ABSOLUTE unset default to relative to now
+}
+
+
+/// # Summary
+/// Determines how long to sleep between iterations. The sleep interval is randomly longer by up to the jitter, so instances sharing a schedule do not hit the source simultaneously.
+///
+/// # Arguments
+/// - `sleep_interval`: minimum sleep duration in seconds
+/// - `sleep_jitter`: maximum random addition in seconds
+///
+/// # Returns
+/// - sleep duration between `sleep_interval` and `sleep_interval + sleep_jitter`, inclusive
+fn sleep_duration(sleep_interval: u64, sleep_jitter: u64) -> std::time::Duration
+{
+    return std::time::Duration::from_secs(sleep_interval + rand::random_range(0..=sleep_jitter));
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+
+    #[test]
+    fn unknown_excluded_event_type_fails_at_startup()
+    {
+        assert!(matches!(validate_config(&Config {EXCLUDED_EVENT_TYPES: Some(vec!["Off".to_owned(), "Flights".to_owned()]), ..Config::default()}), Err(Error::ExcludedEventTypeUnknown(s)) if s == "Flights"));
+        assert!(validate_config(&Config {EXCLUDED_EVENT_TYPES: Some(vec!["off".to_owned(), "NoSummary".to_owned()]), ..Config::default()}).is_ok()); // case-insensitive
+    }
+
+
+    #[test]
+    fn email_alarm_without_recipient_fails_at_startup()
+    {
+        assert!(matches!(validate_config(&Config {ALARM_ACTION: Some(AlarmAction::Email), ..Config::default()}), Err(Error::AlarmEmailMissing)));
+        assert!(matches!(validate_config(&Config {ALARM_ACTION: Some(AlarmAction::Email), ALARM_EMAIL: Some(String::new()), ..Config::default()}), Err(Error::AlarmEmailMissing))); // empty is missing
+        assert!(validate_config(&Config {ALARM_ACTION: Some(AlarmAction::Email), ALARM_EMAIL: Some("crew@example.com".to_owned()), ..Config::default()}).is_ok());
+    }
+
+
+    #[test]
+    fn sleep_jitter_stays_in_bounds()
+    {
+        let sleep_durations: Vec<std::time::Duration> = (0..1000).map(|_| sleep_duration(60, 5)).collect(); // enough samples to hit both bounds
+
+
+        assert!(sleep_durations.iter().all(|d| std::time::Duration::from_secs(60) <= *d && *d <= std::time::Duration::from_secs(65)));
+        assert!(sleep_durations.contains(&std::time::Duration::from_secs(60))); // lower bound inclusive
+        assert!(sleep_durations.contains(&std::time::Duration::from_secs(65))); // upper bound inclusive
+        assert!((0..100).all(|_| sleep_duration(60, 0) == std::time::Duration::from_secs(60))); // no jitter: exact interval
+    }
+
+
+    #[test]
+    fn email_alarm_without_recipient_is_fine_if_alarms_disabled()
+    {
+        assert!(validate_config(&Config {ALARM_ACTION: Some(AlarmAction::Email), DISABLE_ALARMS: Some(true), ..Config::default()}).is_ok());
+    }
+
+
+    #[test]
+    fn archive_end_absolute_overrides_relative()
+    {
+        let now: chrono::DateTime<chrono::Utc> = "2030-01-15T12:00:00Z".parse().unwrap();
+        let archive_end_absolute: chrono::DateTime<chrono::Utc> = "2029-06-01T00:00:00Z".parse().unwrap();
+
+
+        assert_eq!(determine_archive_end_dt(&Config::default(), now), "2030-01-08T12:00:00Z".parse::<chrono::DateTime<chrono::Utc>>().unwrap()); // unset: relative to now
+        assert_eq!(determine_archive_end_dt(&Config {ARCHIVE_END_ABSOLUTE: Some(archive_end_absolute), ..Config::default()}, now), archive_end_absolute);
+        assert_eq!(determine_archive_end_dt(&Config {ARCHIVE_END_ABSOLUTE: Some(archive_end_absolute), ..Config::default()}, now + chrono::Duration::days(100)), archive_end_absolute); // independent of now, reprocessing is reproducible
     }
 }
\ No newline at end of file
@@ -0,0 +1,55 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::error::*;
+
+
+/// # Summary
+/// Deletes all events from the database tables "Event" and "Event_backup", so the next update pulls the whole calendar again like on first start. Airport and country data is preserved.
+///
+/// # Arguments
+/// - `db`: database connection pool
+///
+/// # Returns
+/// - number of deleted events or error
+pub fn reset_db(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> Result<usize, ResetDbError>
+{
+    const EVENT_QUERY: [&str; 2] = // query string for Event tables
+    [
+        "DELETE FROM Event;", // delete all events
+        "DELETE FROM Event_backup;", // delete backup as well, otherwise it could be restored
+    ];
+    let rows_affected: usize; // number of deleted events
+
+
+    let mut db_con = db.get()?; // get connection
+    let db_tx = db_con.transaction()?; // start transaction so automatic rollback on error
+    rows_affected = db_tx.execute(EVENT_QUERY[0], ())?;
+    db_tx.execute(EVENT_QUERY[1], ())?;
+    db_tx.commit()?; // commit transaction
+
+    return Ok(rows_affected);
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn events_and_backup_are_deleted_airports_kept()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        seed_event(&db, "event_1", Some("OFF DAY (X)"), "2030-01-01", "2030-01-02", "FRA");
+        seed_event(&db, "event_2", Some("OFF DAY (X)"), "2030-01-02", "2030-01-03", "FRA");
+        db.get().unwrap().execute("INSERT INTO Event_backup (uid, summary, start_dt, end_dt, location, description, rrule) SELECT uid, summary, start_dt, end_dt, location, description, rrule FROM Event;", ()).unwrap(); // backup could be restored otherwise
+
+
+        assert_eq!(reset_db(&db).unwrap(), 2);
+        assert_eq!((count_rows(&db, "Event"), count_rows(&db, "Event_backup")), (0, 0));
+        assert_eq!((count_rows(&db, "Airport"), count_rows(&db, "Country")), (2, 1)); // airport data preserved
+        assert_eq!(reset_db(&db).unwrap(), 0); // already empty
+    }
+}
\ No newline at end of file
@@ -0,0 +1,27 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+
+
+/// # Summary
+/// Restores the active events backed up in database table "Event_backup" before the last update. Replaces all active events currently in table "Event", archived events remain untouched.
+///
+/// # Arguments
+/// - `db_con`: database connection, usually the transaction of the suspicious update
+/// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+///
+/// # Returns
+/// - number of restored events or error
+pub fn restore_events(db_con: &rusqlite::Connection, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> Result<usize, rusqlite::Error>
+{
+    const EVENT_QUERY: [&str; 2] = // query string for Event table
+    [
+        "DELETE FROM Event WHERE ? < end_dt;", // delete all active events, meaning events newer than end of archive
+        "INSERT OR REPLACE INTO Event (uid, summary, start_dt, end_dt, location, description, rrule) SELECT uid, summary, start_dt, end_dt, location, description, rrule FROM Event_backup;", // restore backed up events
+    ];
+    let rows_restored: usize; // number of restored events
+
+
+    db_con.execute(EVENT_QUERY[0], (archive_end_dt.to_rfc3339(),))?; // must convert to iso8601, see update_events
+    rows_restored = db_con.execute(EVENT_QUERY[1], ())?;
+
+    return Ok(rows_restored);
+}
\ No newline at end of file
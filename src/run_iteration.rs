@@ -0,0 +1,82 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+
+
+/// # Summary
+/// Runs 1 iteration on a separate thread and waits for it at most `iteration_timeout`. A hanging thread can not be killed, so after a timeout it keeps running in the background. To never run 2 iterations at the same time, the new iteration is skipped as long as the previous one has not finished.
+///
+/// # Arguments
+/// - `iteration`: iteration to run
+/// - `iteration_timeout`: maximum duration to wait for the iteration
+/// - `iteration_handle`: thread of the previous iteration, replaced by the thread of this iteration if it is started
+///
+/// # Returns
+/// - outcome of the iteration
+pub fn run_iteration<F, T>(iteration: F, iteration_timeout: std::time::Duration, iteration_handle: &mut Option<std::thread::JoinHandle<()>>) -> IterationOutcome<T>
+where
+    F: FnOnce() -> T + Send + 'static,
+    T: Send + 'static,
+{
+    let (tx, rx) = std::sync::mpsc::channel(); // channel to receive iteration result
+
+
+    if let Some(handle) = iteration_handle && !handle.is_finished() {return IterationOutcome::Skipped;} // previous iteration still hangs: do not start another one
+
+    *iteration_handle = Some(std::thread::spawn(move || {let _ = tx.send(iteration());})); // if receiver gave up already result is discarded
+    match rx.recv_timeout(iteration_timeout)
+    {
+        Ok(o) => return IterationOutcome::Finished(o),
+        Err(std::sync::mpsc::RecvTimeoutError::Timeout) => return IterationOutcome::TimedOut,
+        Err(std::sync::mpsc::RecvTimeoutError::Disconnected) => return IterationOutcome::Panicked, // thread panicked, panic hook logged details
+    }
+}
+
+
+/// # Summary
+/// Outcome of an iteration run with timeout.
+#[derive(Debug, Eq, PartialEq)]
+pub enum IterationOutcome<T>
+{
+    Finished(T), // finished within timeout, with result
+    Panicked, // thread panicked without result
+    Skipped, // not started, previous iteration still running
+    TimedOut, // did not finish within timeout, keeps running in background
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+
+
+    #[test]
+    fn slow_iteration_times_out_and_blocks_next_until_finished()
+    {
+        let mut iteration_handle: Option<std::thread::JoinHandle<()>> = None;
+        let iterations_started: std::sync::Arc<std::sync::atomic::AtomicUsize> = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0)); // number of iterations that actually ran
+        let iteration = |duration: std::time::Duration, result: u8|
+        {
+            let iterations_started: std::sync::Arc<std::sync::atomic::AtomicUsize> = iterations_started.clone();
+            move || {iterations_started.fetch_add(1, std::sync::atomic::Ordering::SeqCst); std::thread::sleep(duration); result}
+        };
+
+
+        assert_eq!(run_iteration(iteration(std::time::Duration::from_millis(500), 1), std::time::Duration::from_millis(50), &mut iteration_handle), IterationOutcome::TimedOut);
+        assert_eq!(run_iteration(iteration(std::time::Duration::ZERO, 2), std::time::Duration::from_millis(50), &mut iteration_handle), IterationOutcome::Skipped); // previous still running
+        assert_eq!(iterations_started.load(std::sync::atomic::Ordering::SeqCst), 1); // skipped iteration never ran
+
+        while !iteration_handle.as_ref().unwrap().is_finished() {std::thread::sleep(std::time::Duration::from_millis(10));} // wait for slow iteration to finish
+        assert_eq!(run_iteration(iteration(std::time::Duration::ZERO, 3), std::time::Duration::from_secs(5), &mut iteration_handle), IterationOutcome::Finished(3)); // finished previous iteration does not block
+        assert_eq!(iterations_started.load(std::sync::atomic::Ordering::SeqCst), 2);
+    }
+
+
+    #[test]
+    fn panicking_iteration_has_no_result()
+    {
+        let mut iteration_handle: Option<std::thread::JoinHandle<()>> = None;
+
+
+        assert_eq!(run_iteration(|| -> u8 {panic!("iteration failed");}, std::time::Duration::from_secs(5), &mut iteration_handle), IterationOutcome::Panicked);
+    }
+}
\ No newline at end of file
@@ -0,0 +1,73 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use crate::update_db::*;
+
+
+/// # Summary
+/// Spawns a background thread that downloads airport and country data again every `refresh_interval` and updates the database, so long running instances pick up changes without delaying calendar updates. The pool makes database access thread-safe. Failed refreshes are logged and retried next interval, so the thread only ends if it panics.
+///
+/// # Arguments
+/// - `http_client`: http client to download with
+/// - `airport_data_url`: url to download airport data from
+/// - `country_data_url`: url to download country data from
+/// - `db`: database connection pool
+/// - `refresh_interval`: duration to sleep before every refresh
+/// - `min_parse_success_ratio`: minimum ratio of successfully parsed rows, otherwise database is not updated
+///
+/// # Returns
+/// - handle of the refresh thread
+pub fn spawn_airport_refresh(http_client: reqwest::blocking::Client, airport_data_url: String, country_data_url: String, db: r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, refresh_interval: std::time::Duration, min_parse_success_ratio: f64) -> std::thread::JoinHandle<()>
+{
+    return std::thread::spawn(move || loop
+    {
+        std::thread::sleep(refresh_interval);
+        log::info!("Refreshing airport and country data...");
+        if let Err(e) = update_airports(&http_client, &airport_data_url, &db, min_parse_success_ratio) // download airport data, parse csv, update database
+        {
+            log::warn!("Refreshing airport database failed with: {e}\nContinuing with potentially outdated data.");
+        }
+        if let Err(e) = update_countries(&http_client, &country_data_url, &db, min_parse_success_ratio) // download country data, parse csv, update database
+        {
+            log::warn!("Refreshing country database failed with: {e}\nContinuing with potentially outdated data.");
+        }
+    });
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::airport_resolver::*;
+    use crate::build_http_client::*;
+    use crate::config::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn airport_data_is_fetched_again_every_interval()
+    {
+        const AIRPORT_HEADER: &str = "id,ident,type,name,latitude_deg,longitude_deg,elevation_ft,continent,iso_country,iso_region,municipality,scheduled_service,gps_code,iata_code,local_code,home_link,wikipedia_link,keywords\n";
+        const COUNTRIES: &str = "id,code,name,continent,wikipedia_link,keywords\n1,DE,Germany,EU,,\n";
+        let db = test_db();
+        seed_country(&db, 1, "DE", "Germany");
+        let (url, requests) = serve(vec!
+        [
+            (Some("text/csv"), format!("{AIRPORT_HEADER}1,EDDF,large_airport,Rhein-Main Airport,50,8,,EU,DE,DE-HE,Frankfurt,yes,EDDF,FRA,FRA,,,\n").into_bytes()),
+            (Some("text/csv"), COUNTRIES.as_bytes().to_vec()),
+            (Some("text/csv"), format!("{AIRPORT_HEADER}1,EDDF,large_airport,Frankfurt am Main Airport,50,8,,EU,DE,DE-HE,Frankfurt am Main,yes,EDDF,FRA,FRA,,,\n").into_bytes()), // renamed
+            (Some("text/csv"), COUNTRIES.as_bytes().to_vec()),
+        ]);
+        let start: std::time::Instant = std::time::Instant::now();
+
+
+        let handle: std::thread::JoinHandle<()> = spawn_airport_refresh(build_http_client(&Config::default()).unwrap(), format!("{url}/airports.csv"), format!("{url}/countries.csv"), db.clone(), std::time::Duration::from_secs(1), 0.9);
+        for path in ["/airports.csv", "/countries.csv", "/airports.csv", "/countries.csv"] // 2 refreshes
+        {
+            assert!(requests.recv_timeout(std::time::Duration::from_secs(10)).unwrap().starts_with(&format!("GET {path} ")));
+        }
+
+        assert!(std::time::Duration::from_secs(2) <= start.elapsed()); // waited interval before each refresh, startup download is main's job
+        assert!(!handle.is_finished()); // keeps refreshing
+        assert_eq!(AirportResolver::new(&db, IcaoSourceColumn::GpsCode).lookup_iata("FRA").map(|row| row.airport_name), Some("Frankfurt am Main Airport".to_owned())); // second airport download is in database before countries are requested
+    }
+}
\ No newline at end of file
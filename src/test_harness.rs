@@ -0,0 +1,331 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use icalendar::{Component, EventLike};
+use crate::config::*;
+use crate::event_counts::*;
+use crate::event_type_patterns::*;
+use crate::load_calendar::*;
+use crate::transform_calendar::*;
+
+
+/// # Summary
+/// Creates an in-memory database with all migrations applied. The pool holds exactly 1 connection, because every in-memory connection would otherwise open its own empty database.
+///
+/// # Returns
+/// - database connection pool
+pub fn test_db() -> r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>
+{
+    static DB_MIGRATIONS_DIR: include_dir::Dir = include_dir::include_dir!("./db_migrations/"); // database migrations directory, same as main_inner
+    let db: r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>; // database connection pool
+
+
+    db = r2d2::Pool::builder().max_size(1).build(r2d2_sqlite::SqliteConnectionManager::memory()).expect("Creating in-memory database failed.");
+    rusqlite_migration::Migrations::from_directory(&DB_MIGRATIONS_DIR).expect("Loading database migrations failed.")
+        .to_latest(&mut db.get().expect("Getting database connection failed."))
+        .expect("Running database migrations failed.");
+
+    return db;
+}
+
+
+/// # Summary
+/// Inserts an event into database table "Event" like update_events would, datetimes in rfc3339 or dates in "%Y-%m-%d".
+///
+/// # Arguments
+/// - `db`: database connection pool
+/// - `uid`: event uid
+/// - `summary`: event summary, None for malformed events
+/// - `start_dt`: event start
+/// - `end_dt`: event end
+/// - `location`: event location, usually IATA code
+pub fn seed_event(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, uid: &str, summary: Option<&str>, start_dt: &str, end_dt: &str, location: &str)
+{
+    db.get().expect("Getting database connection failed.")
+        .execute("INSERT INTO Event (uid, summary, start_dt, end_dt, location, description) VALUES (?, ?, ?, ?, ?, '');", (uid, summary, start_dt, end_dt, location))
+        .expect("Seeding event failed.");
+}
+
+
+/// # Summary
+/// Inserts an airport into database table "Airport" with all columns not needed for lookups filled with placeholders.
+///
+/// # Arguments
+/// - `db`: database connection pool
+/// - `id`: airport id
+/// - `iata_code`: IATA code
+/// - `gps_code`: GPS code, usually ICAO location
+/// - `ident`: identifier
+/// - `local_code`: local code
+/// - `name`: airport name
+/// - `municipality`: city
+/// - `iso_country`: country code
+pub fn seed_airport(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, id: u32, iata_code: Option<&str>, gps_code: Option<&str>, ident: &str, local_code: Option<&str>, name: &str, municipality: &str, iso_country: &str)
+{
+    db.get().expect("Getting database connection failed.")
+        .execute("INSERT INTO Airport (id, ident, type, name, latitude_deg, longitude_deg, continent, iso_country, iso_region, municipality, scheduled_service, gps_code, iata_code, local_code) VALUES (?, ?, 'large_airport', ?, 0, 0, 'EU', ?, ?, ?, 1, ?, ?, ?);", rusqlite::params![id, ident, name, iso_country, format!("{iso_country}-XX"), municipality, gps_code, iata_code, local_code])
+        .expect("Seeding airport failed.");
+}
+
+
+/// # Summary
+/// Returns the number of rows in a database table.
+///
+/// # Arguments
+/// - `db`: database connection pool
+/// - `table`: table name
+///
+/// # Returns
+/// - number of rows
+pub fn count_rows(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, table: &str) -> usize
+{
+    return db.get().expect("Getting database connection failed.").query_one(format!("SELECT COUNT(*) FROM {table};").as_str(), (), |row| row.get(0)).expect("Counting rows failed.");
+}
+
+
+/// # Summary
+/// Inserts a country into database table "Country".
+///
+/// # Arguments
+/// - `db`: database connection pool
+/// - `id`: country id
+/// - `code`: country code
+/// - `name`: country name
+pub fn seed_country(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, id: u32, code: &str, name: &str)
+{
+    db.get().expect("Getting database connection failed.")
+        .execute("INSERT INTO Country (id, code, name, continent) VALUES (?, ?, ?, 'EU');", (id, code, name))
+        .expect("Seeding country failed.");
+}
+
+
+/// # Summary
+/// Seeds Frankfurt and Munich with their country, enough for most flight and location tests.
+///
+/// # Arguments
+/// - `db`: database connection pool
+pub fn seed_airports_default(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>)
+{
+    seed_country(db, 1, "DE", "Germany");
+    seed_airport(db, 1, Some("FRA"), Some("EDDF"), "EDDF", Some("FRA"), "Frankfurt am Main Airport", "Frankfurt am Main", "DE");
+    seed_airport(db, 2, Some("MUC"), Some("EDDM"), "EDDM", Some("MUC"), "Munich Airport", "Munich", "DE");
+}
+
+
+/// # Summary
+/// Returns the built-in event type patterns.
+///
+/// # Returns
+/// - compiled built-in patterns
+pub fn patterns_default() -> EventTypePatterns
+{
+    return EventTypePatterns::load("./config/does_not_exist.toml").expect("Compiling built-in event type patterns failed."); // file does not exist: built-in only
+}
+
+
+/// # Summary
+/// Datetime archive ends in tests, far in the past so seeded events are active unless stated otherwise.
+///
+/// # Returns
+/// - archive end datetime
+pub fn archive_end_dt() -> chrono::DateTime<chrono::Utc>
+{
+    return chrono::DateTime::parse_from_rfc3339("2000-01-01T00:00:00Z").expect("Parsing archive end failed.").with_timezone(&chrono::Utc);
+}
+
+
+/// # Summary
+/// Runs the offline part of an iteration: loads the calendar from the database and transforms it with the built-in patterns.
+///
+/// # Arguments
+/// - `db`: database connection pool, seeded with events and airports
+/// - `config`: configuration, contains transformation settings
+///
+/// # Returns
+/// - output calendar in ical format and event counts
+pub fn run_pipeline(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, config: &Config) -> (String, EventCounts)
+{
+    let input_calendar: icalendar::Calendar = load_calendar(db).expect("Loading calendar failed.");
+    let (output_calendar, event_counts) = transform_calendar(input_calendar, db, &archive_end_dt(), config, &patterns_default());
+
+
+    return (output_calendar.to_string(), event_counts);
+}
+
+
+/// # Summary
+/// Returns the unfolded content lines of the event with `uid` in a calendar in ical format, so tests can assert on single properties and its alarms.
+///
+/// # Arguments
+/// - `calendar`: calendar in ical format
+/// - `uid`: uid of event to extract
+///
+/// # Returns
+/// - content lines from BEGIN:VEVENT to END:VEVENT, empty if not found
+pub fn event_lines(calendar: &str, uid: &str) -> Vec<String>
+{
+    let calendar_unfolded: String = calendar.replace("\r\n ", "").replace("\r\n\t", ""); // unfold long lines
+    let mut event: Vec<String> = Vec::new(); // current event's lines
+
+
+    for line in calendar_unfolded.lines()
+    {
+        if line == "BEGIN:VEVENT" {event.clear();}
+        event.push(line.to_owned());
+        if line == "END:VEVENT" && event.iter().any(|s| s == &format!("UID:{uid}")) {return event;}
+    }
+
+    return Vec::new();
+}
+
+
+/// # Summary
+/// Returns the values of all content lines with property name `name`, parameters ignored, for example all TRIGGER values of an event including its alarms.
+///
+/// # Arguments
+/// - `lines`: unfolded content lines
+/// - `name`: property name
+///
+/// # Returns
+/// - property values, unescaped
+pub fn property_values(lines: &[String], name: &str) -> Vec<String>
+{
+    return lines.iter()
+        .filter(|line| line[..line.find([';', ':']).unwrap_or(line.len())] == *name)
+        .filter_map(|line| line.split_once(':').map(|(_, value)| value.replace("\\n", "\n").replace("\\N", "\n").replace("\\,", ",").replace("\\;", ";").replace("\\\\", "\\")))
+        .collect();
+}
+
+
+/// # Summary
+/// Returns a unique temporary path per call, so tests running in parallel do not share files.
+///
+/// # Arguments
+/// - `name`: file or directory name suffix
+///
+/// # Returns
+/// - temporary path, not created
+pub fn temp_path(name: &str) -> std::path::PathBuf
+{
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0); // unique per call
+
+
+    return std::env::temp_dir().join(format!("{}_{}_{}_{name}", env!("CARGO_PKG_NAME"), std::process::id(), COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed)));
+}
+
+
+/// # Summary
+/// Serves fixed http responses on localhost, 1 per connection in order, so downloads can be tested offline. Received requests are forwarded including headers.
+///
+/// # Arguments
+/// - `responses`: (content type, body) per request, None content type omits the header
+///
+/// # Returns
+/// - base url of server and receiver of raw requests
+pub fn serve(responses: Vec<(Option<&'static str>, Vec<u8>)>) -> (String, std::sync::mpsc::Receiver<String>)
+{
+    let listener: std::net::TcpListener = std::net::TcpListener::bind("127.0.0.1:0").expect("Binding test server failed.");
+    let url: String = format!("http://{}", listener.local_addr().expect("Getting test server address failed."));
+    let (tx, rx) = std::sync::mpsc::channel(); // forward requests to test
+
+
+    std::thread::spawn(move ||
+    {
+        for (content_type, body) in responses
+        {
+            let Ok((mut stream, _)) = listener.accept() else {return;};
+            let mut request: Vec<u8> = Vec::new(); // raw request, only headers because downloads are GET
+            let mut buffer: [u8; 1024] = [0; 1024];
+            while !request.ends_with(b"\r\n\r\n")
+            {
+                match std::io::Read::read(&mut stream, &mut buffer)
+                {
+                    Ok(0) | Err(_) => break,
+                    Ok(n) => request.extend_from_slice(&buffer[..n]),
+                }
+            }
+            let _ = tx.send(String::from_utf8_lossy(&request).into_owned());
+
+            let mut response: Vec<u8> = format!("HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n", body.len()).into_bytes();
+            if let Some(s) = content_type {response.extend(format!("Content-Type: {s}\r\n").into_bytes());}
+            response.extend(b"\r\n");
+            response.extend(body);
+            let _ = std::io::Write::write_all(&mut stream, &response);
+        }
+    });
+
+    return (url, rx);
+}
+
+
+/// # Summary
+/// Returns the value of header `name` in a raw http request, compared case-insensitively.
+///
+/// # Arguments
+/// - `request`: raw http request
+/// - `name`: header name
+///
+/// # Returns
+/// - header value or None if not found
+pub fn request_header(request: &str, name: &str) -> Option<String>
+{
+    return request.lines()
+        .filter_map(|line| line.split_once(':'))
+        .find(|(k, _)| k.trim().eq_ignore_ascii_case(name))
+        .map(|(_, v)| v.trim().to_owned());
+}
+
+
+/// # Summary
+/// Minimal valid calendar in ical format with 1 event, to serve as download.
+///
+/// # Returns
+/// - calendar in ical format
+pub fn calendar_minimal() -> String
+{
+    return "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:test\r\nBEGIN:VEVENT\r\nUID:event\r\nDTSTAMP:20300101T000000Z\r\nDTSTART:20300101T100000Z\r\nDTEND:20300101T110000Z\r\nSUMMARY:OFF DAY (X)\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n".to_owned();
+}
+
+
+/// # Summary
+/// Creates an event like load_calendar would, from rfc3339 datetimes or "%Y-%m-%d" dates.
+///
+/// # Arguments
+/// - `summary`: event summary
+/// - `start_dt`: event start
+/// - `end_dt`: event end
+/// - `location`: event location, usually IATA code
+///
+/// # Returns
+/// - event
+pub fn test_event(summary: &str, start_dt: &str, end_dt: &str, location: &str) -> icalendar::Event
+{
+    let mut calendar_event: icalendar::Event = icalendar::Event::new();
+
+
+    calendar_event.uid("event").summary(summary).location(location).description("");
+    match (chrono::DateTime::parse_from_rfc3339(start_dt), chrono::DateTime::parse_from_rfc3339(end_dt))
+    {
+        (Ok(start), Ok(end)) => {calendar_event.starts(start.with_timezone(&chrono::Utc)).ends(end.with_timezone(&chrono::Utc));},
+        _ => {calendar_event.starts(chrono::NaiveDate::parse_from_str(start_dt, "%Y-%m-%d").expect("Parsing start failed.")).ends(chrono::NaiveDate::parse_from_str(end_dt, "%Y-%m-%d").expect("Parsing end failed."));},
+    }
+
+    return calendar_event;
+}
+
+
+/// # Summary
+/// Returns the unfolded content lines of a single event, so tests of single transformations can assert on properties and alarms.
+///
+/// # Arguments
+/// - `calendar_event`: event
+///
+/// # Returns
+/// - content lines from BEGIN:VEVENT to END:VEVENT
+pub fn event_to_lines(calendar_event: icalendar::Event) -> Vec<String>
+{
+    let uid: String = calendar_event.get_uid().unwrap_or_default().to_owned();
+    let mut calendar: icalendar::Calendar = icalendar::Calendar::new();
+
+
+    calendar.push(calendar_event);
+    return event_lines(&calendar.to_string(), &uid);
+}
\ No newline at end of file
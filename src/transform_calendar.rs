@@ -0,0 +1,214 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+use icalendar::Component;
+use rayon::prelude::*;
+use crate::airport_resolver::*;
+use crate::config::*;
+use crate::event_counts::*;
+use crate::event_type::*;
+use crate::event_type_patterns::*;
+use crate::group_duties::*;
+use crate::transform_calendar_event::*;
+
+
+/// # Summary
+/// Transforms the input calendar into the output calendar. Determines the event type of each event and transforms it accordingly, events of types in `EXCLUDED_EVENT_TYPES` are omitted. If `GROUP_DUTIES` is set, additionally adds an event spanning each duty from briefing to last flight. Components that are not events are forwarded unchanged. Does not download or write anything, only reads airport data from the database.
+///
+/// # Arguments
+/// - `input_calendar`: input calendar, usually loaded from the database
+/// - `db`: database connection pool, used for airport lookups
+/// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+/// - `config`: configuration, contains transformation settings
+/// - `event_type_patterns`: compiled patterns to determine event types
+///
+/// # Returns
+/// - transformed output calendar and the number of events per event type in it
+pub fn transform_calendar(input_calendar: icalendar::Calendar, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>, config: &Config, event_type_patterns: &EventTypePatterns) -> (icalendar::Calendar, EventCounts)
+{
+    let airport_resolver: AirportResolver = AirportResolver::new(db, config.ICAO_SOURCE_COLUMN.clone().unwrap_or(IcaoSourceColumn::GpsCode)); // if ICAO_SOURCE_COLUMN unset default to gps_code, resolves iata locations, caches lookups for this conversion
+    let mut calendar_events: Vec<(EventType, icalendar::Event)>; // input calendar events with their event types
+    let duty_events: Vec<icalendar::Event>; // events grouping a whole duty, only if GROUP_DUTIES
+    let event_counts: EventCounts; // number of events per event type in output calendar
+    let mut output_calendar: icalendar::Calendar = icalendar::Calendar::new(); // transformed output calendar
+    let other_components: Vec<icalendar::CalendarComponent>; // input calendar components that are not events
+
+
+    (calendar_events, other_components) = input_calendar.components.into_par_iter().partition_map(|calendar_component| // determine event types once, so they can be reused for transformation and grouping
+    {
+        match calendar_component
+        {
+            icalendar::CalendarComponent::Event(calendar_event) =>
+            {
+                match calendar_event.get_summary()
+                {
+                    Some(s) => rayon::iter::Either::Left((EventType::determine_event_type(s.to_owned(), event_type_patterns), calendar_event)),
+                    None => // no summary: malformed, do not mix up with unknown summaries
+                    {
+                        log::warn!("Event {} has no summary, source event is malformed. Only doing minimum transformation.", calendar_event.get_uid().unwrap_or_default());
+                        rayon::iter::Either::Left((EventType::NoSummary, calendar_event))
+                    },
+                }
+            },
+            _ => rayon::iter::Either::Right(calendar_component), // if not event: forward unchanged later
+        }
+    });
+    if let Some(excluded_event_types) = &config.EXCLUDED_EVENT_TYPES // remove excluded event types completely, archived or not, before grouping so they don't show up anywhere
+    {
+        let calendar_events_len: usize = calendar_events.len(); // number of events before filtering
+        calendar_events.retain(|(event_type, _)| !event_type.is_excluded(excluded_event_types));
+        log::debug!("Excluded {} events.", calendar_events_len - calendar_events.len());
+    }
+    event_counts = EventCounts::count(&calendar_events, archive_end_dt); // count after excluding, so counts match output calendar
+    if config.GROUP_DUTIES.unwrap_or(false) // if GROUP_DUTIES unset default to false
+    {
+        duty_events = group_duties(&calendar_events, config.GROUP_DUTIES_MAX_GAP.unwrap_or(chrono::Duration::hours(3))); // if GROUP_DUTIES_MAX_GAP unset default to 3 h
+        log::debug!("Grouped {} duties.", duty_events.len());
+    }
+    else
+    {
+        duty_events = Vec::new();
+    }
+
+
+    output_calendar.name("DLH Duty Plan"); // set calendar name
+    if let Some(method) = &config.OUTPUT_CALENDAR_METHOD {output_calendar.append_property(("METHOD", method.as_str()));} // set method if configured, otherwise omit
+    output_calendar.components = calendar_events.into_par_iter().map(|(event_type, calendar_event)| // go through all calendar events and change them as needed
+    {
+        match event_type // transform according to event type
+        {
+            EventType::Briefing => transform_briefing(calendar_event, &airport_resolver, config, archive_end_dt).into(),
+            EventType::Deadhead {flight_iata, departure_iata, destination_iata} => transform_deadhead(calendar_event, flight_iata, departure_iata, destination_iata, &airport_resolver, config, archive_end_dt).into(),
+            EventType::Flight {flight_iata, departure_iata, destination_iata} => transform_flight(calendar_event, flight_iata, departure_iata, destination_iata, &airport_resolver, config, archive_end_dt).into(),
+            EventType::Ground {category, description} => transform_ground(calendar_event, category, description, &airport_resolver, config, archive_end_dt).into(),
+            EventType::Holiday => transform_holiday(calendar_event, config, archive_end_dt).into(),
+            EventType::Hotel {name} => transform_hotel(calendar_event, name, &airport_resolver, config, archive_end_dt).into(),
+            EventType::Layover => transform_layover(calendar_event, &airport_resolver, config, archive_end_dt).into(),
+            EventType::NoSummary => transform_unknown(calendar_event, config, archive_end_dt).into(),
+            EventType::Off => transform_off(calendar_event, config, archive_end_dt).into(),
+            EventType::Pickup => transform_pickup(calendar_event, &airport_resolver, config, archive_end_dt).into(),
+            EventType::Reserve {description} => transform_reserve(calendar_event, description, &airport_resolver, config, archive_end_dt).into(),
+            EventType::Sickness => transform_sickness(calendar_event, config, archive_end_dt).into(),
+            EventType::Unknown => transform_unknown(calendar_event, config, archive_end_dt).into(),
+        }
+    }).collect();
+    output_calendar.components.extend(duty_events.into_iter().map(|duty_event| icalendar::CalendarComponent::from(transform_unknown(duty_event, config, archive_end_dt)))); // add grouping events, only do minimum
+    output_calendar.components.extend(other_components); // forward non-events unchanged
+
+    return (output_calendar, event_counts);
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn event_without_summary_gets_minimum_transformation()
+    {
+        let db = test_db();
+        seed_event(&db, "no_summary", None, "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA");
+
+
+        let (output_calendar, event_counts) = run_pipeline(&db, &Config::default());
+        let no_summary: Vec<String> = event_lines(&output_calendar, "no_summary");
+
+        assert!(!no_summary.is_empty()); // kept in output calendar
+        assert!(property_values(&no_summary, "SUMMARY").is_empty()); // no summary invented
+        assert!(!no_summary.contains(&"BEGIN:VALARM".to_owned())); // no alarm without summary
+        assert_eq!(event_counts.per_event_type.get("NoSummary"), Some(&1)); // not mixed up with unknown summaries
+        assert_eq!(event_counts.per_event_type.get("Unknown"), None);
+    }
+
+
+    #[test]
+    fn excluded_event_types_are_omitted()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        seed_event(&db, "flight", Some("LH 100: FRA-MUC"), "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA");
+        seed_event(&db, "off", Some("OFF DAY (X)"), "2030-01-02", "2030-01-03", "FRA");
+        seed_event(&db, "sickness", Some("SICKNESS (K)"), "2030-01-03", "2030-01-04", "FRA");
+
+
+        let (output_calendar, event_counts) = run_pipeline(&db, &Config {EXCLUDED_EVENT_TYPES: Some(vec!["off".to_owned(), "Sickness".to_owned()]), ..Config::default()}); // case-insensitive
+
+        assert!(!event_lines(&output_calendar, "flight").is_empty());
+        assert!(event_lines(&output_calendar, "off").is_empty());
+        assert!(event_lines(&output_calendar, "sickness").is_empty());
+        assert_eq!(event_counts.total, 1); // counts match output calendar
+    }
+
+
+    #[test]
+    fn transform_calendar_directly()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let mut input_calendar: icalendar::Calendar = icalendar::Calendar::new();
+        input_calendar.push(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA").uid("flight").done());
+        input_calendar.push(test_event("SOMETHING NEW", "2030-01-02T10:00:00Z", "2030-01-02T11:00:00Z", "FRA").uid("unknown").done());
+        input_calendar.push(icalendar::Todo::new().uid("todo").summary("not an event").done());
+
+
+        let (output_calendar, event_counts) = transform_calendar(input_calendar, &db, &archive_end_dt(), &Config::default(), &patterns_default());
+        let output_calendar: String = output_calendar.to_string();
+
+        assert_eq!(property_values(&event_lines(&output_calendar, "flight"), "SUMMARY"), vec!["LH100: EDDF ✈ EDDM".to_owned()]);
+        assert_eq!(property_values(&event_lines(&output_calendar, "flight"), "LOCATION"), vec!["EDDF: Germany, Frankfurt am Main Airport".to_owned()]);
+        assert_eq!(property_values(&event_lines(&output_calendar, "unknown"), "SUMMARY"), vec!["SOMETHING NEW".to_owned()]); // unknown: summary unchanged
+        assert!(output_calendar.contains("BEGIN:VTODO")); // non-events forwarded unchanged
+        assert!(output_calendar.contains(":DLH Duty Plan\r\n")); // calendar name
+        assert_eq!((event_counts.total, event_counts.per_event_type.get("Flight"), event_counts.per_event_type.get("Unknown")), (2, Some(&1), Some(&1)));
+    }
+
+
+    #[test]
+    fn hotel_keeps_name_and_has_no_alarm()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        seed_event(&db, "hotel", Some("htl (Maritim proArte Hotel)"), "2030-01-01T12:00:00Z", "2030-01-02T12:00:00Z", "FRA"); // keyword lowercase
+
+
+        let (output_calendar, event_counts) = run_pipeline(&db, &Config::default());
+        let hotel: Vec<String> = event_lines(&output_calendar, "hotel");
+
+        assert_eq!(property_values(&hotel, "SUMMARY"), vec!["Hotel: Maritim proArte Hotel".to_owned()]); // name case kept
+        assert_eq!(property_values(&hotel, "LOCATION"), vec!["Germany, Frankfurt am Main".to_owned()]); // country and city
+        assert!(!hotel.contains(&"BEGIN:VALARM".to_owned()));
+        assert_eq!(event_counts.per_event_type.get("Hotel"), Some(&1));
+    }
+
+
+    #[test]
+    fn private_events_are_masked()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        seed_event(&db, "flight", Some("LH 100: FRA-MUC"), "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA");
+        seed_event(&db, "holiday", Some("ABSENCE (URLAUB)"), "2030-01-02", "2030-01-03", "FRA");
+        seed_event(&db, "off", Some("OFF DAY (X)"), "2030-01-03", "2030-01-04", "FRA");
+        seed_event(&db, "reserve", Some("RESERVE (RES)"), "2030-01-04T06:00:00Z", "2030-01-04T18:00:00Z", "FRA");
+        seed_event(&db, "sickness", Some("SICKNESS (KO)"), "2030-01-05", "2030-01-06", "FRA");
+        let summary = |output_calendar: &str, uid: &str| property_values(&event_lines(output_calendar, uid), "SUMMARY").concat();
+
+
+        let (output_calendar, _) = run_pipeline(&db, &Config {MASK_PRIVATE_EVENTS: Some(true), KEEP_ORIGINAL_SUMMARY: Some(true), ..Config::default()});
+        for uid in ["holiday", "off", "sickness"]
+        {
+            assert_eq!(summary(&output_calendar, uid), "Unavailable", "uid \"{uid}\""); // unset: default label
+            assert_eq!(property_values(&event_lines(&output_calendar, uid), "X-ORIGINAL-SUMMARY"), vec!["Unavailable".to_owned()], "uid \"{uid}\""); // reason not revealed
+        }
+        assert_eq!(summary(&output_calendar, "flight"), "LH100: EDDF ✈ EDDM");
+        assert_eq!(property_values(&event_lines(&output_calendar, "flight"), "X-ORIGINAL-SUMMARY"), vec!["LH 100: FRA-MUC".to_owned()]);
+        assert_ne!(summary(&output_calendar, "reserve"), "Unavailable"); // not private
+
+        let (output_calendar, _) = run_pipeline(&db, &Config {MASK_PRIVATE_EVENTS: Some(true), PRIVATE_EVENT_LABEL: Some("Busy".to_owned()), ..Config::default()});
+        assert_eq!(summary(&output_calendar, "off"), "Busy");
+
+        let (output_calendar, _) = run_pipeline(&db, &Config::default());
+        assert_ne!(summary(&output_calendar, "off"), "Unavailable"); // unset: not masked
+    }
+}
\ No newline at end of file
@@ -1,163 +1,234 @@
 // Copyright (c) 2024 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
 use icalendar::{Component, EventLike};
+use crate::airport_resolver::*;
+use crate::config::*;
+use crate::dateperhapstime_to_datetime::*;
 use crate::dateperhapstime_to_string::*;
 use crate::is_archived::*;
 
 
 /// # Summary
-/// Transforms the briefing event. Additionally to the minimum actions changes summary to "Briefing", changes IATA location to ICAO location, and adds alarms at -1,5 h, -1 h, and -15 min.
+/// Transforms the briefing event. Additionally to the minimum actions changes summary to "Briefing" with the leading local time according to `TIME_PREFIX_MODE`, changes IATA location to ICAO location, and adds alarms at -1,5 h, -1 h, and -15 min.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
-/// - `db`: airport database connection pool
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains alarm, location, and tagging settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_briefing(mut calendar_event: icalendar::Event, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_briefing(mut calendar_event: icalendar::Event, airport_resolver: &AirportResolver, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
-    calendar_event.summary("Briefing");
-    if let Some(row) = lookup_iata(calendar_event.get_location().unwrap_or_default().to_owned(), db) // if iata location found
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
+    calendar_event.summary(format!("{}Briefing", format_time_prefix(calendar_event.get_summary().unwrap_or_default(), config)).as_str()); // summary still original, take time from it
+    if let Some(s) = format_location(calendar_event.get_location().unwrap_or_default(), "{icao}: {country}, {name}", airport_resolver, config) // change iata location to icao location
     {
-        if let Some(s) = row.airport_gps_code // if entry contains icao location
-        {
-            calendar_event.location(format!("{}: {}, {}", s, row.country_name, row.airport_name).as_str()); // change iata location to icao location
-        }
+        calendar_event.location(s.as_str());
     } // otherwise just keep original data
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(90))); // add alarm at -1,5 h
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::hours(-1))); // add alarm at -1 h
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(-15))); // add alarm at -15 min
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(90), config); // add alarm at -1,5 h
+    add_alarm(&mut calendar_event, chrono::Duration::hours(-1), config); // add alarm at -1 h
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(-15), config); // add alarm at -15 min
 
     return calendar_event;
 }
 
 
 /// # Summary
-/// Transforms the deadhead event. Additionally to the minimum actions changes summary format, changes IATA locations to departure ICAO location only, and adds an alarm at -1,5 h and -35 min.
+/// Transforms the deadhead event. Additionally to the minimum actions changes summary format with departure and destination separated by `FLIGHT_SEPARATOR`, changes IATA locations to departure ICAO location only, sets X-DESTINATION to the destination ICAO location, adds the destination airport's name and country to the description, and adds an alarm at -1,5 h and -35 min.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
 /// - `flight_iata`: flight IATA code
 /// - `departure_iata`: departure IATA code
 /// - `destination_iata`: destination IATA code
-/// - `db`: airport database connection pool
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains alarm, location, separator, and tagging settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_deadhead(mut calendar_event: icalendar::Event, flight_iata: String, departure_iata: String, destination_iata: String, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_deadhead(mut calendar_event: icalendar::Event, flight_iata: String, departure_iata: String, destination_iata: String, airport_resolver: &AirportResolver, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
-    calendar_event.summary(format!("DEADHEAD {flight_iata}: {} ✈ {}", try_iata_to_icao(departure_iata.to_owned(), db), try_iata_to_icao(destination_iata.to_owned(), db)).as_str()); // change summary format
-    if let Some(row) = lookup_iata(departure_iata, db) // if iata location found
+    let flight_separator: &str = config.FLIGHT_SEPARATOR.as_deref().unwrap_or("✈"); // separator between departure and destination, if FLIGHT_SEPARATOR unset default to airplane
+
+
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
+    calendar_event.summary(format!("DEADHEAD {flight_iata}: {} {flight_separator} {}", airport_resolver.try_iata_to_icao(&departure_iata), airport_resolver.try_iata_to_icao(&destination_iata)).as_str()); // change summary format
+    if let Some(s) = format_location(&departure_iata, "{icao}: {country}, {name}", airport_resolver, config) // change iata location to icao location
     {
-        if let Some(s) = row.airport_gps_code // if entry contains icao location
-        {
-            calendar_event.location(format!("{}: {}, {}", s, row.country_name, row.airport_name).as_str()); // change iata location to icao location
-        }
+        calendar_event.location(s.as_str());
     } // otherwise just keep original data
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(90))); // add alarm at -1,5 h
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(-35))); // add alarm at -35 min
+    if let Some(s) = format_location(&destination_iata, "{icao}: {country}, {name}", airport_resolver, config) // location can only hold departure, so carry destination separately for positioning chains
+    {
+        calendar_event.add_property("X-DESTINATION", s.as_str());
+    }
+    if let Some(row) = airport_resolver.lookup_iata(&destination_iata) // resolve destination, summary only contains its code
+    {
+        append_description(&mut calendar_event, format!("To: {}, {}", row.airport_name, row.country_name).as_str());
+    }
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(90), config); // add alarm at -1,5 h
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(-35), config); // add alarm at -35 min
 
     return calendar_event;
 }
 
 
 /// # Summary
-/// Transforms the flight event. Additionally to the minimum actions changes summary format, changes IATA locations to departure ICAO location only, and adds an alarm at -30 min.
+/// Transforms the flight event. Additionally to the minimum actions changes summary format with departure and destination separated by `FLIGHT_SEPARATOR`, changes IATA locations to departure ICAO location only, adds the destination airport's name and country to the description, and adds an alarm at -30 min. If `TAG_REDEYE` is set, prefixes the summary with "🌙" if the departure time is between 22:00 and 05:00 UTC. The airport database has no timezones, so local time is not known.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
 /// - `flight_iata`: flight IATA code
 /// - `departure_iata`: departure IATA code
 /// - `destination_iata`: destination IATA code
-/// - `db`: airport database connection pool
-/// -- `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains alarm, location, separator, and tagging settings
+/// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_flight(mut calendar_event: icalendar::Event, flight_iata: String, departure_iata: String, destination_iata: String, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_flight(mut calendar_event: icalendar::Event, flight_iata: String, departure_iata: String, destination_iata: String, airport_resolver: &AirportResolver, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
-    calendar_event.summary(format!("{flight_iata}: {} ✈ {}", try_iata_to_icao(departure_iata.to_owned(), db), try_iata_to_icao(destination_iata.to_owned(), db)).as_str()); // change summary format
-    if let Some(row) = lookup_iata(departure_iata, db) // if iata location found
+    let flight_separator: &str = config.FLIGHT_SEPARATOR.as_deref().unwrap_or("✈"); // separator between departure and destination, if FLIGHT_SEPARATOR unset default to airplane
+
+
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
+    calendar_event.summary(format!("{flight_iata}: {} {flight_separator} {}", airport_resolver.try_iata_to_icao(&departure_iata), airport_resolver.try_iata_to_icao(&destination_iata)).as_str()); // change summary format
+    if config.TAG_REDEYE.unwrap_or(false) && let Some(Ok(Some(departure_dt))) = calendar_event.get_start().map(dateperhapstime_to_datetime) // if TAG_REDEYE unset default to false
     {
-        if let Some(s) = row.airport_gps_code // if entry contains icao location
+        let departure_hour: u32 = chrono::Timelike::hour(&departure_dt); // utc departure hour
+        if 22 <= departure_hour || departure_hour < 5 // departure at night: tag as red-eye
         {
-            calendar_event.location(format!("{}: {}, {}", s, row.country_name, row.airport_name).as_str()); // change iata location to icao location
+            calendar_event.summary(format!("🌙 {}", calendar_event.get_summary().unwrap_or_default()).as_str());
         }
+    }
+    if let Some(s) = format_location(&departure_iata, "{icao}: {country}, {name}", airport_resolver, config) // change iata location to icao location
+    {
+        calendar_event.location(s.as_str());
     } // otherwise just keep original data
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(-30))); // add alarm at -30 min
+    if let Some(row) = airport_resolver.lookup_iata(&destination_iata) // resolve destination, summary only contains its code
+    {
+        append_description(&mut calendar_event, format!("To: {}, {}", row.airport_name, row.country_name).as_str());
+    }
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(-30), config); // add alarm at -30 min
 
     return calendar_event;
 }
 
 
 /// # Summary
-/// Transforms the ground event. Additionally to the minimum actions changes summary format, changes IATA locations to ICAO location only, and adds alarms at -1 h and -15 min.
+/// Transforms the ground event. Additionally to the minimum actions changes summary format, changes IATA locations to ICAO location only, and adds alarms at -1 h and -15 min. Simulator sessions need more preparation and additionally get an alarm at `SIMULATOR_ALARM`. The category is mapped to a shorter version by the built-in mapping extended by `GROUND_CATEGORY_MAP`. If the category is empty, `GROUND_DEFAULT_CATEGORY` is used instead if set.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
 /// - `category`: category of the event
 /// - `description`: description of the event
-/// - `db`: airport database connection pool
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains alarm, location, tagging settings, category mapping, and default category
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_ground(mut calendar_event: icalendar::Event, category: String, description: String, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_ground(mut calendar_event: icalendar::Event, mut category: String, description: String, airport_resolver: &AirportResolver, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
-    if category == "" {calendar_event.summary(description.as_str());} // if category is empty: change summary to description
+    let mut category_mapping: std::collections::HashMap<String, String> = std::collections::HashMap::from
+    ([
+        ("GENERALEVENT".to_owned(), "".to_owned()), // unnecessary, no information value with this
+        ("MANDATORY TRAINING".to_owned(), "Training".to_owned()),
+        ("MEDICAL EVENT".to_owned(), "Medical".to_owned()),
+    ]); // map categories to shorter and prettier versions, if not in here forward category unchanged, keys uppercase to compare case-insensitively
+    let is_simulator: bool = category.eq_ignore_ascii_case("SIMULATOR"); // determine before mapping, mapping might rename category
+
+
+    category_mapping.extend(config.GROUND_CATEGORY_MAP.iter().flatten().map(|(k, v)| (k.to_uppercase(), v.to_owned()))); // configured mapping extends and overrides built-in mapping
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
+    if let Some(s) = category_mapping.get(&category.to_uppercase()) {category = s.to_owned();} // map category if known
+    if category == "" {category = config.GROUND_DEFAULT_CATEGORY.clone().unwrap_or_default();} // if category is empty: use default category, if GROUND_DEFAULT_CATEGORY unset keep empty
+    if category == "" {calendar_event.summary(description.as_str());} // if category is still empty: change summary to description
     else {calendar_event.summary(format!("{category}: {description}").as_str());} // otherwise: change summary format only slightly
-    if let Some(row) = lookup_iata(calendar_event.get_location().unwrap_or_default().to_owned(), db) // if iata location found
+    if let Some(s) = format_location(calendar_event.get_location().unwrap_or_default(), "{country}, {city}", airport_resolver, config) // change iata location to country and city
     {
-        calendar_event.location(format!("{}, {}", row.country_name, row.airport_municipality).as_str()); // change iata location to country and city
+        calendar_event.location(s.as_str());
     } // otherwise just keep original data
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::hours(-1))); // add alarm at -1 h
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(-15))); // add alarm at -15 min
+    if is_simulator // simulator needs earlier preparation
+    {
+        add_alarm(&mut calendar_event, config.SIMULATOR_ALARM.unwrap_or(chrono::Duration::hours(-2)), config); // if SIMULATOR_ALARM unset default to -2 h
+    }
+    add_alarm(&mut calendar_event, chrono::Duration::hours(-1), config); // add alarm at -1 h
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(-15), config); // add alarm at -15 min
 
     return calendar_event;
 }
 
 
 /// # Summary
-/// Transforms the holiday event. Additionally to the minimum actions changes summary to "Holiday".
+/// Transforms the holiday event. Additionally to the minimum actions changes summary to "Holiday". If `MASK_PRIVATE_EVENTS` is set, the summary is replaced by `PRIVATE_EVENT_LABEL`.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
+/// - `config`: configuration, contains tagging and masking settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_holiday(mut calendar_event: icalendar::Event, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_holiday(mut calendar_event: icalendar::Event, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
     calendar_event.location(""); // holiday does not need a location
     calendar_event.summary("Holiday");
+    mask_private_event(&mut calendar_event, config); // if MASK_PRIVATE_EVENTS: hide reason of absence
 
     return calendar_event;
 }
 
 
 /// # Summary
-/// Transforms the layover event. Additionally to the minimum actions changes summary to "Layover" and changes IATA location to ICAO location.
+/// Transforms the hotel event. Additionally to the minimum actions changes summary to "Hotel: {name}" and changes IATA location to country and city. Adds no alarm.
+///
+/// # Arguments
+/// - `calendar_event`: the calendar event to transform
+/// - `name`: name of the hotel
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains location and tagging settings
+/// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+///
+/// # Returns
+/// - the transformed calendar event
+pub fn transform_hotel(mut calendar_event: icalendar::Event, name: String, airport_resolver: &AirportResolver, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+{
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
+    calendar_event.summary(format!("Hotel: {name}").as_str());
+    if let Some(s) = format_location(calendar_event.get_location().unwrap_or_default(), "{country}, {city}", airport_resolver, config) // change iata location to country and city
+    {
+        calendar_event.location(s.as_str());
+    } // otherwise just keep original data
+
+    return calendar_event;
+}
+
+/// # Summary
+/// Transforms the layover event. Additionally to the minimum actions changes summary to "Layover" with its duration appended, for example "Layover (14h30m)", and changes IATA location to ICAO location. All-day layovers have no duration appended.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
-/// - `db`: airport database connection pool
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains location and tagging settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_layover(mut calendar_event: icalendar::Event, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_layover(mut calendar_event: icalendar::Event, airport_resolver: &AirportResolver, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
-    calendar_event.summary("Layover");
-    if let Some(row) = lookup_iata(calendar_event.get_location().unwrap_or_default().to_owned(), db) // if iata location found
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
+    match get_duration(&calendar_event) // append duration if event has datetimes
+    {
+        Some(duration) => {calendar_event.summary(format!("Layover ({}h{:02}m)", duration.num_hours(), duration.num_minutes().rem_euclid(60)).as_str());},
+        None => {calendar_event.summary("Layover");}, // all-day or invalid: no duration
+    }
+    if let Some(s) = format_location(calendar_event.get_location().unwrap_or_default(), "{country}, {city}", airport_resolver, config) // change iata location to country and city
     {
-        calendar_event.location(format!("{}, {}", row.country_name, row.airport_municipality).as_str()); // change iata location to country and city
+        calendar_event.location(s.as_str());
     } // otherwise just keep original data
 
     return calendar_event;
@@ -165,45 +236,48 @@ pub fn transform_layover(mut calendar_event: icalendar::Event, db: &r2d2::Pool<r
 
 
 /// # Summary
-/// Transforms the off event. Additionally to the minimum actions changes summary to "Off".
+/// Transforms the off event. Additionally to the minimum actions changes summary to "Off". If `MASK_PRIVATE_EVENTS` is set, the summary is replaced by `PRIVATE_EVENT_LABEL`.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
+/// - `config`: configuration, contains tagging and masking settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_off(mut calendar_event: icalendar::Event, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_off(mut calendar_event: icalendar::Event, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
     calendar_event.location(""); // off day does not need a location
     calendar_event.summary("Off");
+    mask_private_event(&mut calendar_event, config); // if MASK_PRIVATE_EVENTS: hide reason of absence
 
     return calendar_event;
 }
 
 
 /// # Summary
-/// Transforms the pickup event. Additionally to the minimum actions changes summary to "Pickup", changes IATA location to ICAO location, and adds alarms at -1 h, -15 min, and -1 min.
+/// Transforms the pickup event. Additionally to the minimum actions changes summary to "Pickup" with the leading local time according to `TIME_PREFIX_MODE`, changes IATA location to ICAO location, and adds alarms at -1 h, -15 min, and -1 min.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
-/// - `db`: airport database connection pool
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains alarm, location, and tagging settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_pickup(mut calendar_event: icalendar::Event, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_pickup(mut calendar_event: icalendar::Event, airport_resolver: &AirportResolver, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
-    calendar_event.summary("Pickup");
-    if let Some(row) = lookup_iata(calendar_event.get_location().unwrap_or_default().to_owned(), db) // if iata location found
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
+    calendar_event.summary(format!("{}Pickup", format_time_prefix(calendar_event.get_summary().unwrap_or_default(), config)).as_str()); // summary still original, take time from it
+    if let Some(s) = format_location(calendar_event.get_location().unwrap_or_default(), "{country}, {city}", airport_resolver, config) // change iata location to country and city
     {
-        calendar_event.location(format!("{}, {}", row.country_name, row.airport_municipality).as_str()); // change iata location to country and city
+        calendar_event.location(s.as_str());
     } // otherwise just keep original data
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::hours(-1))); // add alarm at -1 h
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(-15))); // add alarm at -15 min
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(-1))); // add alarm at -1 min
+    add_alarm(&mut calendar_event, chrono::Duration::hours(-1), config); // add alarm at -1 h
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(-15), config); // add alarm at -15 min
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(-1), config); // add alarm at -1 min
 
     return calendar_event;
 }
@@ -215,14 +289,15 @@ pub fn transform_pickup(mut calendar_event: icalendar::Event, db: &r2d2::Pool<r2
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
 /// - `description`: description of the event
-/// - `db`: airport database connection pool
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains alarm, location, and tagging settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_reserve(mut calendar_event: icalendar::Event, description: String, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_reserve(mut calendar_event: icalendar::Event, description: String, airport_resolver: &AirportResolver, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
     match description.as_str() // change summary format
     {
         _ if description.starts_with("RB") => {calendar_event.summary("On Call");},
@@ -232,56 +307,85 @@ pub fn transform_reserve(mut calendar_event: icalendar::Event, description: Stri
         _ => {panic!("Reserve event's description has invalid value \"{description}\" even though `RESERVE_PATTERN` should prevent this.");},
     }
 
-    if let Some(row) = lookup_iata(calendar_event.get_location().unwrap_or_default().to_owned(), db) // if iata location found
+    if let Some(s) = format_location(calendar_event.get_location().unwrap_or_default(), "{country}, {city}", airport_resolver, config) // change iata location to country and city
     {
-        calendar_event.location(format!("{}, {}", row.country_name, row.airport_municipality).as_str()); // change iata location to country and city
+        calendar_event.location(s.as_str());
     } // otherwise just keep original data
-    calendar_event.alarm(icalendar::Alarm::display(calendar_event.get_summary().unwrap_or_default(), chrono::Duration::minutes(-15))); // add alarm at -15 min
+    add_alarm(&mut calendar_event, chrono::Duration::minutes(-15), config); // add alarm at -15 min
 
     return calendar_event;
 }
 
 
 /// # Summary
-/// Transforms a sick day. Additionally to the minimum actions changes summary to "Sickness".
+/// Transforms a sick day. Additionally to the minimum actions changes summary to "Sickness". If `MASK_PRIVATE_EVENTS` is set, the summary is replaced by `PRIVATE_EVENT_LABEL`.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
+/// - `config`: configuration, contains tagging and masking settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_sickness(mut calendar_event: icalendar::Event, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_sickness(mut calendar_event: icalendar::Event, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
-    calendar_event = transform_unknown(calendar_event, archive_end_dt); // always do minimum before specific actions
+    calendar_event = transform_unknown(calendar_event, config, archive_end_dt); // always do minimum before specific actions
     calendar_event.location(""); // sickness does not need a location
     calendar_event.summary("Sickness");
+    mask_private_event(&mut calendar_event, config); // if MASK_PRIVATE_EVENTS: hide reason of absence
 
     return calendar_event;
 }
 
 
 /// # Summary
-/// Transforms an unknown event. Only does the minimum: removes the unnecessary description and checks if the event is archived.
+/// Transforms an unknown event. Only does the minimum: sets DTSTAMP and LAST-MODIFIED to the conversion time, keeps the original summary if `KEEP_ORIGINAL_SUMMARY` is set, removes the unnecessary description, tags the ISO week if `TAG_ISO_WEEK` is set, extends events shorter than `MIN_EVENT_DURATION`, and checks if the event is archived.
 ///
 /// # Arguments
 /// - `calendar_event`: the calendar event to transform
+/// - `config`: configuration, contains tagging settings
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
 ///
 /// # Returns
 /// - the transformed calendar event
-pub fn transform_unknown(mut calendar_event: icalendar::Event, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
+pub fn transform_unknown(mut calendar_event: icalendar::Event, config: &Config, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> icalendar::Event
 {
+    let now_str: String = chrono::Utc::now().format("%Y%m%dT%H%M%SZ").to_string(); // conversion time in ical utc format
+
+
+    calendar_event.add_property("DTSTAMP", now_str.as_str()); // make sure every event has a fresh timestamp
+    calendar_event.add_property("LAST-MODIFIED", now_str.as_str()); // so clients detect and refresh changed events
+    if config.KEEP_ORIGINAL_SUMMARY.unwrap_or(false) && let Some(s) = calendar_event.get_summary().map(|s| s.to_owned()) // if KEEP_ORIGINAL_SUMMARY unset default to false, runs before specific actions change the summary
+    {
+        calendar_event.add_property("X-ORIGINAL-SUMMARY", s.as_str()); // keep untransformed summary for traceability
+    }
     calendar_event.description(""); // remove unnecessary description from mytime
+    if config.TAG_ISO_WEEK.unwrap_or(false) // if TAG_ISO_WEEK unset default to false
+    {
+        match calendar_event.get_start() // tag with iso week and weekday of local start date, for example "2024-W32 Thu"
+        {
+            Some(icalendar::DatePerhapsTime::Date(d)) => append_description(&mut calendar_event, d.format("%G-W%V %a").to_string().as_str()),
+            Some(icalendar::DatePerhapsTime::DateTime(icalendar::CalendarDateTime::Floating(dt))) => append_description(&mut calendar_event, dt.format("%G-W%V %a").to_string().as_str()),
+            Some(icalendar::DatePerhapsTime::DateTime(icalendar::CalendarDateTime::Utc(dt))) => append_description(&mut calendar_event, dt.format("%G-W%V %a").to_string().as_str()),
+            Some(icalendar::DatePerhapsTime::DateTime(icalendar::CalendarDateTime::WithTimezone {date_time, tzid: _})) => append_description(&mut calendar_event, date_time.format("%G-W%V %a").to_string().as_str()),
+            None => {}, // no start: nothing to tag
+        }
+    }
+    if let Some(min_event_duration) = config.MIN_EVENT_DURATION // if MIN_EVENT_DURATION unset default to not extending events
+        && let (Some(Ok(Some(start))), Some(Ok(Some(end)))) = (calendar_event.get_start().map(dateperhapstime_to_datetime), calendar_event.get_end().map(dateperhapstime_to_datetime)) // all-day events have no datetimes and are skipped
+        && end - start < min_event_duration // too short, for example pickups with 0 duration render awkwardly
+    {
+        calendar_event.ends(start + min_event_duration); // extend to minimum duration, keep start
+    }
 
-    match dateperhapstime_to_string(calendar_event.get_end().expect(format!("Calendar event {} \"{}\" has no end datetime even though it is mandatory upon saving in the database.", calendar_event.get_uid().unwrap_or_default(), calendar_event.get_summary().unwrap_or_default()).as_str()))
+    match dateperhapstime_to_string(calendar_event.get_end().expect(format!("Calendar event {} \"{}\" has no end datetime even though it is mandatory upon saving in the database.", calendar_event.get_uid().unwrap_or_default(), calendar_event.get_summary().unwrap_or_default()).as_str()), None) // floating datetimes have already been converted when saving in database
     {
         Ok(o) =>
         {
             if is_archived(o.as_str(),  archive_end_dt) // if table is not empty and event is archived: do not insert
                 .expect(format!("Parsing \"{o}\" to datetime failed even though it should have been properly formatted in dateperhapstime_to_string.").as_str())
             {
-                calendar_event.description("archived event 🔒"); // if event is archived: state in description
+                append_description(&mut calendar_event, "archived event 🔒"); // if event is archived: state in description
             } // set end date to string
         },
         Err(e) =>
@@ -296,57 +400,490 @@ pub fn transform_unknown(mut calendar_event: icalendar::Event, archive_end_dt: &
 
 
 /// # Summary
-/// Takes an IATA location and tries to get the ICAO location, country, and airport name. If not exactly 1 entry could be found, returns None.
+/// Appends a line to the description of a calendar event. If the description is empty, the line becomes the description.
 ///
 /// # Arguments
-/// - `iata`: IATA location
+/// - `calendar_event`: the calendar event to append to
+/// - `line`: line to append
+fn append_description(calendar_event: &mut icalendar::Event, line: &str)
+{
+    let description: String = match calendar_event.get_description()
+    {
+        Some(s) if !s.is_empty() => format!("{s}\n{line}"), // existing description: append as new line
+        _ => line.to_owned(), // no description yet: replace
+    };
+    calendar_event.description(description.as_str());
+}
+
+
+/// # Summary
+/// Adds an alarm to a calendar event. The alarm's action is determined by `ALARM_ACTION`, defaulting to display. Email alarms are sent to `ALARM_EMAIL`. If `DISABLE_ALARMS` is set, does nothing.
+///
+/// # Arguments
+/// - `calendar_event`: the calendar event to add the alarm to
+/// - `trigger`: when the alarm triggers relative to the event's start
+/// - `config`: configuration, contains alarm settings
+fn add_alarm(calendar_event: &mut icalendar::Event, trigger: chrono::Duration, config: &Config)
+{
+    let alarm: icalendar::Alarm; // alarm to add
+    let summary: String = calendar_event.get_summary().unwrap_or_default().to_owned(); // alarm text
+
+
+    if config.DISABLE_ALARMS.unwrap_or(false) {return;} // if DISABLE_ALARMS unset default to false
+
+    match config.ALARM_ACTION.as_ref().unwrap_or(&AlarmAction::Display) // if ALARM_ACTION unset default to display
+    {
+        AlarmAction::Audio => alarm = icalendar::Alarm::audio(trigger),
+        AlarmAction::Display => alarm = icalendar::Alarm::display(summary.as_str(), trigger),
+        AlarmAction::Email =>
+        {
+            let mut email_alarm: icalendar::Alarm = icalendar::Alarm::display(summary.as_str(), trigger); // library has no email constructor, so build from display and change action
+            email_alarm.add_property("ACTION", "EMAIL");
+            email_alarm.add_property("SUMMARY", summary.as_str()); // email alarms require summary as subject
+            email_alarm.add_property("ATTENDEE", format!("mailto:{}", config.ALARM_EMAIL.as_deref().unwrap_or_default()).as_str()); // recipient, validated at startup
+            alarm = email_alarm;
+        },
+    }
+    calendar_event.alarm(alarm);
+}
+
+
+/// # Summary
+/// If `MASK_PRIVATE_EVENTS` is set, replaces the summary of a private event with `PRIVATE_EVENT_LABEL`, defaulting to "Unavailable". The original summary kept due to `KEEP_ORIGINAL_SUMMARY` is replaced as well, so the reason of absence is not revealed.
+///
+/// # Arguments
+/// - `calendar_event`: the calendar event to mask
+/// - `config`: configuration, contains masking settings
+fn mask_private_event(calendar_event: &mut icalendar::Event, config: &Config)
+{
+    let private_event_label: &str = config.PRIVATE_EVENT_LABEL.as_deref().unwrap_or("Unavailable"); // if PRIVATE_EVENT_LABEL unset default to "Unavailable"
+
+
+    if config.MASK_PRIVATE_EVENTS.unwrap_or(false) // if MASK_PRIVATE_EVENTS unset default to false
+    {
+        calendar_event.summary(private_event_label);
+        if calendar_event.property_value("X-ORIGINAL-SUMMARY").is_some() {calendar_event.add_property("X-ORIGINAL-SUMMARY", private_event_label);} // overwrite, would reveal reason otherwise
+    }
+}
+
+
+/// # Summary
+/// Extracts the leading local time, for example "06:15 LT", from a summary and formats it according to `TIME_PREFIX_MODE`, defaulting to stripping it.
+///
+/// # Arguments
+/// - `summary`: original summary
+/// - `config`: configuration, contains time prefix mode
 ///
 /// # Returns
-/// - ICAO location
-/// - country name
-/// - airport name
-fn lookup_iata(iata: String, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> Option<IataLookupRow>
+/// - formatted time prefix including trailing space, empty if stripped or summary has no leading time
+fn format_time_prefix(summary: &str, config: &Config) -> String
 {
-    const LOOKUP_IATA_QUERY: &str = "SELECT Airport.gps_code AS airport_gps_code, Airport.municipality AS airport_municipality, Country.name AS country_name, Airport.name AS airport_name FROM Airport JOIN Country ON Airport.iso_country = Country.code WHERE Airport.iata_code = ?;"; // query string for iata lookup
+    static TIME_PREFIX_REGEX: std::sync::LazyLock<regex::Regex> = std::sync::LazyLock::new(|| regex::Regex::new(r"^(?P<time>\d{2}:\d{2}) LT ").expect("Compiling time prefix regex failed.")); // leading local time, compiled once instead of for every event
+    let time: chrono::NaiveTime; // leading local time
 
 
-    let db_con = db.get().ok()?; // get connection
-    return db_con.query_one(LOOKUP_IATA_QUERY, (iata,), |row| { Ok(IataLookupRow
+    let Some(captures) = TIME_PREFIX_REGEX.captures(summary) else {return String::new();}; // no leading time: nothing to keep
+    match chrono::NaiveTime::parse_from_str(&captures["time"], "%H:%M")
     {
-        airport_name: row.get("airport_name")?,
-        airport_gps_code: row.get("airport_gps_code")?,
-        airport_municipality: row.get("airport_municipality")?,
-        country_name: row.get("country_name")?
-    })}).ok(); // execute query, if failed return None as if no icao location found
+        Ok(o) => time = o,
+        Err(_) => return String::new(), // invalid time like "25:00": nothing to keep
+    }
+
+    match config.TIME_PREFIX_MODE.as_ref().unwrap_or(&TimePrefixMode::Strip) // if TIME_PREFIX_MODE unset default to strip
+    {
+        TimePrefixMode::Keep => return format!("{} LT ", time.format("%H:%M")),
+        TimePrefixMode::Strip => return String::new(),
+        TimePrefixMode::TwelveHour => return format!("{} LT ", time.format("%-I:%M %p")),
+    }
 }
 
-#[derive(Clone, Debug, Eq, PartialEq, serde::Deserialize, serde::Serialize)]
-pub struct IataLookupRow
+
+/// # Summary
+/// Looks up an IATA location and formats it with `LOCATION_FORMAT`, or with `default_location_format` if unset. Supported placeholders are `{icao}`, `{iata}`, `{country}`, `{city}`, and `{name}`. If the format contains `{icao}` but the airport has no ICAO code, returns None.
+///
+/// # Arguments
+/// - `iata`: IATA location to look up
+/// - `default_location_format`: format to use if `LOCATION_FORMAT` is unset
+/// - `airport_resolver`: resolves IATA locations using the airport database
+/// - `config`: configuration, contains location format
+///
+/// # Returns
+/// - formatted location or None if location could not be found
+fn format_location(iata: &str, default_location_format: &str, airport_resolver: &AirportResolver, config: &Config) -> Option<String>
 {
-    pub airport_name: String, // Airport.name
-    pub airport_gps_code: Option<String>, // Airport.gps_code, icao location
-    pub airport_municipality: String, // Airport.municipality, city
-    pub country_name: String, // Country.name
+    let location_format: &str = config.LOCATION_FORMAT.as_deref().unwrap_or(default_location_format); // if LOCATION_FORMAT unset default to format depending on event type
+    let row: IataLookupRow = airport_resolver.lookup_iata(iata)?; // if iata location not found: keep original data
+
+
+    if location_format.contains("{icao}") && row.airport_gps_code.is_none() {return None;} // format needs icao location but entry has none: keep original data
+
+    return Some(location_format
+        .replace("{icao}", row.airport_gps_code.as_deref().unwrap_or_default())
+        .replace("{iata}", iata)
+        .replace("{country}", row.country_name.as_str())
+        .replace("{city}", row.airport_municipality.as_str())
+        .replace("{name}", row.airport_name.as_str()));
 }
 
 
 /// # Summary
-/// Takes an IATA location and tries to get the ICAO location. If no entry could be found, returns input value unchanged.
+/// Calculates the duration of a calendar event from its start and end. If the event is all-day or start or end could not be converted, returns None.
 ///
 /// # Arguments
-/// - `iata`: IATA location
+/// - `calendar_event`: the calendar event to calculate the duration of
 ///
 /// # Returns
-/// - ICAO location or unchanged input value
-fn try_iata_to_icao(iata: String, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> String
+/// - duration or None
+fn get_duration(calendar_event: &icalendar::Event) -> Option<chrono::Duration>
+{
+    let start: chrono::DateTime<chrono::Utc> = dateperhapstime_to_datetime(calendar_event.get_start()?).ok()??; // all-day or invalid: no duration
+    let end: chrono::DateTime<chrono::Utc> = dateperhapstime_to_datetime(calendar_event.get_end()?).ok()??;
+
+    return Some(end - start);
+}
+
+
+#[cfg(test)]
+mod tests
 {
-    const IATA_TO_ICAO_QUERY: &str = "SELECT gps_code FROM Airport WHERE iata_code = ?;"; // query string for iata to icao lookup
+    use super::*;
+    use crate::test_harness::*;
 
 
-    let db_con = match db.get() // get connection or fallback to return value unchanged
+    #[test]
+    fn layover_duration_is_appended()
     {
-        Ok(o) => o,
-        Err(_) => {return iata;},
-    };
-    return db_con.query_one(IATA_TO_ICAO_QUERY, (&iata,), |row| {row.get("gps_code")}).unwrap_or(iata); // if no icao location found: forward unchanged value
+        let db = test_db();
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+
+
+        assert_eq!(transform_layover(test_event("LAYOVER", "2030-01-01T08:00:00Z", "2030-01-01T22:30:00Z", "FRA"), &airport_resolver, &Config::default(), &archive_end_dt()).get_summary(), Some("Layover (14h30m)"));
+        assert_eq!(transform_layover(test_event("LAYOVER", "2030-01-01T08:00:00Z", "2030-01-01T08:05:00Z", "FRA"), &airport_resolver, &Config::default(), &archive_end_dt()).get_summary(), Some("Layover (0h05m)")); // minutes padded
+        assert_eq!(transform_layover(test_event("LAYOVER", "2030-01-01T08:00:00Z", "2030-01-03T09:00:00Z", "FRA"), &airport_resolver, &Config::default(), &archive_end_dt()).get_summary(), Some("Layover (49h00m)")); // hours not wrapped to days
+        assert_eq!(transform_layover(test_event("LAYOVER", "2030-01-01", "2030-01-02", "FRA"), &airport_resolver, &Config::default(), &archive_end_dt()).get_summary(), Some("Layover")); // all-day: no duration
+    }
+
+
+    #[test]
+    fn flight_separator_default_and_custom()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config_custom: Config = Config {FLIGHT_SEPARATOR: Some("->".to_owned()), ..Config::default()};
+
+
+        assert_eq!(transform_flight(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, &Config::default(), &archive_end_dt()).get_summary(), Some("LH100: EDDF ✈ EDDM"));
+        assert_eq!(transform_flight(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, &config_custom, &archive_end_dt()).get_summary(), Some("LH100: EDDF -> EDDM"));
+        assert_eq!(transform_deadhead(test_event("DH LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, &config_custom, &archive_end_dt()).get_summary(), Some("DEADHEAD LH100: EDDF -> EDDM"));
+    }
+
+
+    #[test]
+    fn ground_default_category()
+    {
+        let db = test_db();
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config_default_category: Config = Config {GROUND_DEFAULT_CATEGORY: Some("Ground".to_owned()), ..Config::default()};
+
+
+        assert_eq!(transform_ground(test_event("GENERALEVENT (MEETING)", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "GENERALEVENT".to_owned(), "MEETING".to_owned(), &airport_resolver, &Config::default(), &archive_end_dt()).get_summary(), Some("MEETING")); // unset: description only
+        assert_eq!(transform_ground(test_event("GENERALEVENT (MEETING)", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "GENERALEVENT".to_owned(), "MEETING".to_owned(), &airport_resolver, &config_default_category, &archive_end_dt()).get_summary(), Some("Ground: MEETING")); // mapped to empty: default category
+        assert_eq!(transform_ground(test_event("MEDICAL EVENT (CHECK)", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "MEDICAL EVENT".to_owned(), "CHECK".to_owned(), &airport_resolver, &config_default_category, &archive_end_dt()).get_summary(), Some("Medical: CHECK")); // category present: default unused
+    }
+
+
+    #[test]
+    fn alarm_actions()
+    {
+        let db = test_db();
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let transform = |config: Config| event_to_lines(transform_reserve(test_event("RESERVE (RES)", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "RES".to_owned(), &airport_resolver, &config, &archive_end_dt())); // reserve has exactly 1 alarm
+
+
+        let display: Vec<String> = transform(Config::default());
+        assert_eq!(property_values(&display, "ACTION"), vec!["DISPLAY".to_owned()]); // default
+        assert_eq!(property_values(&display, "TRIGGER"), vec!["-PT900S".to_owned()]);
+
+        let audio: Vec<String> = transform(Config {ALARM_ACTION: Some(AlarmAction::Audio), ..Config::default()});
+        assert_eq!(property_values(&audio, "ACTION"), vec!["AUDIO".to_owned()]);
+        assert_eq!(property_values(&audio, "TRIGGER"), vec!["-PT900S".to_owned()]);
+
+        let email: Vec<String> = transform(Config {ALARM_ACTION: Some(AlarmAction::Email), ALARM_EMAIL: Some("crew@example.com".to_owned()), ..Config::default()});
+        assert_eq!(property_values(&email, "ACTION"), vec!["EMAIL".to_owned()]);
+        assert_eq!(property_values(&email, "ATTENDEE"), vec!["mailto:crew@example.com".to_owned()]);
+        assert_eq!(property_values(&email, "SUMMARY"), vec!["Reserve Standby".to_owned(), "Reserve Standby".to_owned()]); // event and email subject
+    }
+
+
+    /// # Summary
+    /// Transforms a flight from FRA and a hotel at FRA with `LOCATION_FORMAT` and returns their locations.
+    fn locations(location_format: Option<&str>) -> (String, String)
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config: Config = Config {LOCATION_FORMAT: location_format.map(|s| s.to_owned()), ..Config::default()};
+
+
+        let flight: icalendar::Event = transform_flight(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, &config, &archive_end_dt());
+        let hotel: icalendar::Event = transform_hotel(test_event("HTL (Hotel)", "2030-01-01T12:00:00Z", "2030-01-02T12:00:00Z", "FRA"), "Hotel".to_owned(), &airport_resolver, &config, &archive_end_dt());
+        return (flight.get_location().unwrap_or_default().to_owned(), hotel.get_location().unwrap_or_default().to_owned());
+    }
+
+
+    #[test]
+    fn location_format_default()
+    {
+        assert_eq!(locations(None), ("EDDF: Germany, Frankfurt am Main Airport".to_owned(), "Germany, Frankfurt am Main".to_owned())); // depends on event type
+    }
+
+
+    #[test]
+    fn location_format_icao()
+    {
+        assert_eq!(locations(Some("{icao}")).0, "EDDF");
+    }
+
+
+    #[test]
+    fn location_format_iata()
+    {
+        assert_eq!(locations(Some("{iata}")).0, "FRA");
+    }
+
+
+    #[test]
+    fn location_format_country()
+    {
+        assert_eq!(locations(Some("{country}")).0, "Germany");
+    }
+
+
+    #[test]
+    fn location_format_city()
+    {
+        assert_eq!(locations(Some("{city}")).1, "Frankfurt am Main");
+    }
+
+
+    #[test]
+    fn location_format_name()
+    {
+        assert_eq!(locations(Some("{name} ({iata})")), ("Frankfurt am Main Airport (FRA)".to_owned(), "Frankfurt am Main Airport (FRA)".to_owned())); // overrides every event type
+    }
+
+
+    #[test]
+    fn location_format_icao_missing_keeps_original()
+    {
+        let db = test_db();
+        seed_country(&db, 1, "DE", "Germany");
+        seed_airport(&db, 1, Some("FRA"), None, "DE-0001", None, "Frankfurt am Main Airport", "Frankfurt am Main", "DE"); // no gps code
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+
+
+        assert_eq!(transform_flight(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, &Config::default(), &archive_end_dt()).get_location(), Some("FRA"));
+    }
+
+
+    #[test]
+    fn iso_week_at_year_boundary()
+    {
+        let config: Config = Config {TAG_ISO_WEEK: Some(true), ..Config::default()};
+
+
+        assert_eq!(transform_unknown(test_event("X", "2027-01-01", "2027-01-02", "FRA"), &config, &archive_end_dt()).get_description(), Some("2026-W53 Fri")); // belongs to last week of previous year
+        assert_eq!(transform_unknown(test_event("X", "2024-12-30T10:00:00Z", "2024-12-30T11:00:00Z", "FRA"), &config, &archive_end_dt()).get_description(), Some("2025-W01 Mon")); // belongs to first week of next year
+        assert_eq!(transform_unknown(test_event("X", "2026-01-01T10:00:00Z", "2026-01-01T11:00:00Z", "FRA"), &config, &archive_end_dt()).get_description(), Some("2026-W01 Thu"));
+        assert_eq!(transform_unknown(test_event("X", "2026-01-01T10:00:00Z", "2026-01-01T11:00:00Z", "FRA"), &Config::default(), &archive_end_dt()).get_description(), Some("")); // unset: no tag
+    }
+
+
+    #[test]
+    fn ground_category_map()
+    {
+        let db = test_db();
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config: Config = Config {GROUND_CATEGORY_MAP: Some(std::collections::HashMap::from([("Office Day".to_owned(), "Office".to_owned()), ("medical event".to_owned(), "Doctor".to_owned())])), ..Config::default()};
+        let transform = |category: &str, config: &Config| transform_ground(test_event("X", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), category.to_owned(), "CHECK".to_owned(), &airport_resolver, config, &archive_end_dt()).get_summary().unwrap_or_default().to_owned();
+
+
+        assert_eq!(transform("MEDICAL EVENT", &Config::default()), "Medical: CHECK"); // built-in
+        assert_eq!(transform("MANDATORY TRAINING", &config), "Training: CHECK"); // built-in kept
+        assert_eq!(transform("OFFICE DAY", &config), "Office: CHECK"); // configured, case-insensitive
+        assert_eq!(transform("MEDICAL EVENT", &config), "Doctor: CHECK"); // configured overrides built-in
+        assert_eq!(transform("SIMULATOR", &config), "SIMULATOR: CHECK"); // unmapped: unchanged
+    }
+
+
+    #[test]
+    fn dtstamp_and_last_modified_are_conversion_time()
+    {
+        let t_before: chrono::DateTime<chrono::Utc> = chrono::Utc::now() - chrono::Duration::seconds(1); // DTSTAMP has no fractional seconds
+
+
+        let calendar_event: Vec<String> = event_to_lines(transform_unknown(test_event("X", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA").add_property("DTSTAMP", "20000101T000000Z").done(), &Config::default(), &archive_end_dt()));
+        let dtstamp: Vec<String> = property_values(&calendar_event, "DTSTAMP");
+
+        assert_eq!(dtstamp.len(), 1); // replaced, not added
+        assert_eq!(property_values(&calendar_event, "LAST-MODIFIED"), dtstamp);
+        let dtstamp: chrono::DateTime<chrono::Utc> = chrono::NaiveDateTime::parse_from_str(&dtstamp[0], "%Y%m%dT%H%M%SZ").unwrap().and_utc();
+        assert!(t_before <= dtstamp && dtstamp <= chrono::Utc::now());
+    }
+
+
+    #[test]
+    fn original_summary_is_kept_if_configured()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config: Config = Config {KEEP_ORIGINAL_SUMMARY: Some(true), ..Config::default()};
+        let flight = |config: &Config| event_to_lines(transform_flight(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, config, &archive_end_dt()));
+
+
+        assert_eq!(property_values(&flight(&config), "X-ORIGINAL-SUMMARY"), vec!["LH 100: FRA-MUC".to_owned()]); // untransformed
+        assert_eq!(property_values(&flight(&config), "SUMMARY"), vec!["LH100: EDDF ✈ EDDM".to_owned()]); // summary still transformed
+        assert!(property_values(&flight(&Config::default()), "X-ORIGINAL-SUMMARY").is_empty()); // unset: omitted
+        assert_eq!(property_values(&event_to_lines(transform_off(test_event("OFF DAY (X)", "2030-01-01", "2030-01-02", "FRA"), &Config {MASK_PRIVATE_EVENTS: Some(true), ..config.clone()}, &archive_end_dt())), "X-ORIGINAL-SUMMARY"), vec!["Unavailable".to_owned()]); // masked: original would reveal reason
+    }
+
+
+    #[test]
+    fn redeye_is_tagged_by_utc_departure()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config: Config = Config {TAG_REDEYE: Some(true), ..Config::default()};
+        let flight = |start_dt: &str, end_dt: &str, config: &Config| transform_flight(test_event("LH 100: FRA-MUC", start_dt, end_dt, "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, config, &archive_end_dt()).get_summary().unwrap_or_default().to_owned();
+
+
+        assert_eq!(flight("2030-01-01T23:30:00Z", "2030-01-02T00:30:00Z", &config), "🌙 LH100: EDDF ✈ EDDM");
+        assert_eq!(flight("2030-01-01T04:59:00Z", "2030-01-01T06:00:00Z", &config), "🌙 LH100: EDDF ✈ EDDM");
+        assert_eq!(flight("2030-01-01T09:00:00Z", "2030-01-01T10:00:00Z", &config), "LH100: EDDF ✈ EDDM");
+        assert_eq!(flight("2030-01-01T05:00:00Z", "2030-01-01T06:00:00Z", &config), "LH100: EDDF ✈ EDDM"); // 05:00 is not night anymore
+        assert_eq!(flight("2030-01-01T23:30:00Z", "2030-01-02T00:30:00Z", &Config::default()), "LH100: EDDF ✈ EDDM"); // unset: no tag
+    }
+
+
+    #[test]
+    fn min_event_duration_extends_short_events()
+    {
+        let config: Config = Config {MIN_EVENT_DURATION: Some(chrono::Duration::minutes(15)), ..Config::default()};
+        let start_end = |start_dt: &str, end_dt: &str, config: &Config| {let calendar_event: icalendar::Event = transform_unknown(test_event("X", start_dt, end_dt, "FRA"), config, &archive_end_dt()); (calendar_event.get_start(), calendar_event.get_end())};
+        let dt = |s: &str| Some(icalendar::DatePerhapsTime::DateTime(icalendar::CalendarDateTime::Utc(s.parse().unwrap())));
+
+
+        assert_eq!(start_end("2030-01-01T10:00:00Z", "2030-01-01T10:00:00Z", &config), (dt("2030-01-01T10:00:00Z"), dt("2030-01-01T10:15:00Z"))); // 0 duration: end moved, start kept
+        assert_eq!(start_end("2030-01-01T10:00:00Z", "2030-01-01T10:05:00Z", &config), (dt("2030-01-01T10:00:00Z"), dt("2030-01-01T10:15:00Z")));
+        assert_eq!(start_end("2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", &config), (dt("2030-01-01T10:00:00Z"), dt("2030-01-01T11:00:00Z"))); // long enough: unchanged
+        assert_eq!(start_end("2030-01-01T10:00:00Z", "2030-01-01T10:00:00Z", &Config::default()), (dt("2030-01-01T10:00:00Z"), dt("2030-01-01T10:00:00Z"))); // unset: unchanged
+        assert_eq!(start_end("2030-01-01", "2030-01-02", &config).1, Some(icalendar::DatePerhapsTime::Date(chrono::NaiveDate::from_ymd_opt(2030, 1, 2).unwrap()))); // all-day: unaffected
+    }
+
+
+    #[test]
+    fn disable_alarms_omits_every_alarm()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config: Config = Config {DISABLE_ALARMS: Some(true), SIMULATOR_ALARM: Some(chrono::Duration::hours(-3)), ..Config::default()}; // disabling wins over alarm settings
+        let calendar_events = |config: &Config| vec!
+        [
+            transform_briefing(test_event("06:15 LT BRIEFING FRA", "2030-01-01T06:15:00Z", "2030-01-01T07:15:00Z", "FRA"), &airport_resolver, config, &archive_end_dt()),
+            transform_flight(test_event("LH 100: FRA-MUC", "2030-01-01T08:00:00Z", "2030-01-01T09:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, config, &archive_end_dt()),
+            transform_ground(test_event("SIMULATOR (SIM)", "2030-01-02T08:00:00Z", "2030-01-02T12:00:00Z", "FRA"), "SIMULATOR".to_owned(), "SIM".to_owned(), &airport_resolver, config, &archive_end_dt()),
+            transform_pickup(test_event("05:15 LT PICKUP FRA", "2030-01-01T05:15:00Z", "2030-01-01T05:15:00Z", "FRA"), &airport_resolver, config, &archive_end_dt()),
+        ];
+
+
+        assert!(calendar_events(&config).into_iter().all(|calendar_event| !event_to_lines(calendar_event).contains(&"BEGIN:VALARM".to_owned())));
+        assert!(calendar_events(&Config::default()).into_iter().all(|calendar_event| event_to_lines(calendar_event).contains(&"BEGIN:VALARM".to_owned()))); // unset: alarms added
+    }
+
+
+    #[test]
+    fn destination_is_added_to_description()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config: Config = Config {TAG_ISO_WEEK: Some(true), ..Config::default()};
+
+
+        assert_eq!(transform_flight(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, &Config::default(), &archive_end_dt()).get_description(), Some("To: Munich Airport, Germany"));
+        assert_eq!(transform_deadhead(test_event("DH LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, &Config::default(), &archive_end_dt()).get_description(), Some("To: Munich Airport, Germany"));
+        assert_eq!(transform_flight(test_event("LH 100: FRA-MUC", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "MUC".to_owned(), &airport_resolver, &config, &archive_end_dt()).get_description(), Some("2030-W01 Tue\nTo: Munich Airport, Germany")); // appended after other tags
+        assert_eq!(transform_flight(test_event("LH 100: FRA-XXX", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), "XXX".to_owned(), &airport_resolver, &Config::default(), &archive_end_dt()).get_description(), Some("")); // unknown destination: nothing added
+    }
+
+
+    #[test]
+    fn simulator_gets_additional_alarm()
+    {
+        let db = test_db();
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let triggers = |category: &str, config: &Config| property_values(&event_to_lines(transform_ground(test_event("X", "2030-01-01T10:00:00Z", "2030-01-01T14:00:00Z", "FRA"), category.to_owned(), "SIM".to_owned(), &airport_resolver, config, &archive_end_dt())), "TRIGGER");
+
+
+        assert_eq!(triggers("SIMULATOR", &Config::default()), vec!["-PT7200S".to_owned(), "-PT3600S".to_owned(), "-PT900S".to_owned()]); // unset: -2 h
+        assert_eq!(triggers("SIMULATOR", &Config {SIMULATOR_ALARM: Some(chrono::Duration::hours(-3)), ..Config::default()}), vec!["-PT10800S".to_owned(), "-PT3600S".to_owned(), "-PT900S".to_owned()]);
+        assert_eq!(triggers("SIMULATOR", &Config {GROUND_CATEGORY_MAP: Some(std::collections::HashMap::from([("SIMULATOR".to_owned(), "Sim".to_owned())])), ..Config::default()}).len(), 3); // determined before mapping
+        assert_eq!(triggers("OFFICE DAY", &Config::default()), vec!["-PT3600S".to_owned(), "-PT900S".to_owned()]); // no simulator: no additional alarm
+    }
+
+
+    #[test]
+    fn deadhead_destination_is_kept_separately()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let deadhead = |destination_iata: &str| transform_deadhead(test_event("DH LH 100", "2030-01-01T10:00:00Z", "2030-01-01T11:00:00Z", "FRA"), "LH100".to_owned(), "FRA".to_owned(), destination_iata.to_owned(), &airport_resolver, &Config::default(), &archive_end_dt());
+
+
+        assert_eq!(deadhead("MUC").property_value("X-DESTINATION"), Some("EDDM: Germany, Munich Airport"));
+        assert_eq!(deadhead("MUC").get_location(), Some("EDDF: Germany, Frankfurt am Main Airport")); // location still departure
+        assert_eq!(deadhead("XXX").property_value("X-DESTINATION"), None); // unknown destination: not set
+    }
+
+
+    #[test]
+    fn time_prefix_is_formatted_by_mode()
+    {
+        let config = |time_prefix_mode: TimePrefixMode| Config {TIME_PREFIX_MODE: Some(time_prefix_mode), ..Config::default()};
+
+
+        assert_eq!(format_time_prefix("06:15 LT Briefing FRA", &Config::default()), ""); // unset: strip
+        assert_eq!(format_time_prefix("06:15 LT Briefing FRA", &config(TimePrefixMode::Keep)), "06:15 LT ");
+        assert_eq!(format_time_prefix("06:15 LT Briefing FRA", &config(TimePrefixMode::Strip)), "");
+        assert_eq!(format_time_prefix("06:15 LT Briefing FRA", &config(TimePrefixMode::TwelveHour)), "6:15 AM LT ");
+        assert_eq!(format_time_prefix("18:05 LT Briefing FRA", &config(TimePrefixMode::TwelveHour)), "6:05 PM LT ");
+        assert_eq!(format_time_prefix("00:30 LT Briefing FRA", &config(TimePrefixMode::TwelveHour)), "12:30 AM LT "); // midnight is 12 am
+        for time_prefix_mode in [TimePrefixMode::Keep, TimePrefixMode::Strip, TimePrefixMode::TwelveHour]
+        {
+            assert_eq!(format_time_prefix("Briefing FRA", &config(time_prefix_mode.clone())), "", "{time_prefix_mode:?}"); // no leading time: nothing to keep
+            assert_eq!(format_time_prefix("25:00 LT Briefing FRA", &config(time_prefix_mode)), ""); // invalid time
+        }
+    }
+
+
+    #[test]
+    fn briefing_and_pickup_summary_time_prefix()
+    {
+        let db = test_db();
+        seed_airports_default(&db);
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        let config = |time_prefix_mode: TimePrefixMode| Config {TIME_PREFIX_MODE: Some(time_prefix_mode), ..Config::default()};
+
+
+        assert_eq!(transform_briefing(test_event("06:15 LT Briefing FRA", "2030-01-01T05:15:00Z", "2030-01-01T06:15:00Z", "FRA"), &airport_resolver, &Config::default(), &archive_end_dt()).get_summary(), Some("Briefing")); // unset: strip
+        assert_eq!(transform_briefing(test_event("06:15 LT Briefing FRA", "2030-01-01T05:15:00Z", "2030-01-01T06:15:00Z", "FRA"), &airport_resolver, &config(TimePrefixMode::Keep), &archive_end_dt()).get_summary(), Some("06:15 LT Briefing"));
+        assert_eq!(transform_briefing(test_event("06:15 LT Briefing FRA", "2030-01-01T05:15:00Z", "2030-01-01T06:15:00Z", "FRA"), &airport_resolver, &config(TimePrefixMode::TwelveHour), &archive_end_dt()).get_summary(), Some("6:15 AM LT Briefing"));
+        assert_eq!(transform_pickup(test_event("18:05 LT Pickup FRA", "2030-01-01T17:05:00Z", "2030-01-01T17:35:00Z", "FRA"), &airport_resolver, &config(TimePrefixMode::Keep), &archive_end_dt()).get_summary(), Some("18:05 LT Pickup"));
+        assert_eq!(transform_pickup(test_event("18:05 LT Pickup FRA", "2030-01-01T17:05:00Z", "2030-01-01T17:35:00Z", "FRA"), &airport_resolver, &config(TimePrefixMode::TwelveHour), &archive_end_dt()).get_summary(), Some("6:05 PM LT Pickup"));
+    }
 }
\ No newline at end of file
@@ -1,78 +1,231 @@
 // Copyright (c) 2024 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
-use icalendar::Component;
-use rayon::prelude::*;
+use crate::config::*;
+use crate::download_calendar::*;
 use crate::error::*;
-use crate::event_type::*;
+use crate::event_counts::*;
+use crate::event_type_patterns::*;
+use crate::find_overlaps::*;
+use crate::generate_freebusy::*;
 use crate::load_calendar::*;
-use crate::transform_calendar_event::*;
+use crate::transform_calendar::*;
 use crate::update_db::*;
+use crate::write_file_atomic::*;
 
 
 /// # Summary
-/// Downloads calendar from myTime, parses it, and updates the database table "Event". After that, loads the whole calendar from the database, transforms it, and saves it to a file.
+/// Downloads calendar from myTime, parses it, and updates the database table "Event". After that, loads the whole calendar from the database, transforms it or generates free/busy information if `OUTPUT_MODE` is "freebusy", and saves it to every file in `OUTPUT_CALENDAR_FILEPATH` whose content changed apart from timestamps. Only fails if saving failed for every file. Logs a summary of how many events of which type the output calendar contains.
 ///
 /// # Arguments
 /// - `http_client`: http client
-/// - `input_calendar_url`: calendar source URL
-/// - `output_calendar_filepath`: calendar output file path
+/// - `config`: configuration, contains calendar source URL, calendar output file path, and transformation settings
 /// - `db`: database connection pool
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+/// - `event_type_patterns`: compiled patterns to determine event types
 ///
 /// # Returns
-/// - nothing or error
-pub fn update_calendar(http_client: &reqwest::blocking::Client, input_calendar_url: &str, output_calendar_filepath: &str, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> Result<(), UpdateCalendarError>
+/// - number of events per event type in output calendar or error
+pub fn update_calendar(http_client: &reqwest::blocking::Client, config: &Config, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>, event_type_patterns: &EventTypePatterns) -> Result<EventCounts, UpdateCalendarError>
 {
     const ALERT_TRIGGER_PATTERN: &str = r"PT(?P<t_trigger>[0-9]+)S"; // alert trigger pattern in calendar ical, purposely disregard potential minus sign in front of "PT" to keep it unchanged
+    const PRODID: &str = concat!("-//9-FS//", env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"), "//EN"); // product identifier of output calendar
+    const PRODID_PATTERN: &str = r"(?m)^PRODID:[^\r\n]*"; // product identifier line in calendar ical, library might set its own
+    let event_counts: EventCounts; // number of events per event type in output calendar
     let input_calendar: icalendar::Calendar; // input calendar
-    let mut output_calendar: icalendar::Calendar = icalendar::Calendar::new(); // transformed output calendar
+    let output_calendar: String; // output calendar in ical format, transformed events or free/busy information
+    let mut output_calendar_errors: Vec<std::io::Error> = Vec::new(); // errors while saving output calendar, one per failed destination
 
 
-    update_events(http_client, input_calendar_url, db, archive_end_dt)?;
+    update_events(&download_calendar(http_client, config.INPUT_CALENDAR_URL.as_str(), config.INPUT_CALENDAR_AUTH.as_ref())?, db, archive_end_dt, config.ARCHIVE_MAX_AGE, config.MIN_ACTIVE_EVENTS_RATIO, config.INPUT_TIMEZONE)?; // download calendar and update database with it
     input_calendar = load_calendar(db)?; // load whole calendar from database
+    if config.WARN_ON_OVERLAP.unwrap_or(false) // if WARN_ON_OVERLAP unset default to false
+    {
+        for (event_span_1, event_span_2) in find_overlaps(&input_calendar, archive_end_dt)
+        {
+            log::warn!("Event {event_span_1} overlaps with event {event_span_2}. Roster might contain a double booking.");
+        }
+    }
 
 
-    output_calendar.name("DLH Duty Plan"); // set calendar name
-    output_calendar.components = input_calendar.components.into_par_iter().map(|calendar_component| // go through all calendar components and change them as needed
+    match config.OUTPUT_MODE.as_ref().unwrap_or(&OutputMode::Events) // if OUTPUT_MODE unset default to events
     {
-        match calendar_component
+        OutputMode::Events =>
         {
-            icalendar::CalendarComponent::Event(calendar_event) => // transform event
+            let output_calendar_transformed: icalendar::Calendar; // transformed output calendar
+            (output_calendar_transformed, event_counts) = transform_calendar(input_calendar, db, archive_end_dt, config, event_type_patterns); // transform calendar
+            output_calendar = regex::Regex::new(ALERT_TRIGGER_PATTERN).expect("Compiling alert trigger regex failed.").replace_all(&output_calendar_transformed.to_string(), |captures: &regex::Captures|
             {
-                match EventType::determine_event_type(calendar_event.get_summary().unwrap_or_default().to_owned()) // determine event type, transform accordingly
-                {
-                    EventType::Briefing => transform_briefing(calendar_event, db, archive_end_dt).into(),
-                    EventType::Deadhead {flight_iata, departure_iata, destination_iata} => transform_deadhead(calendar_event, flight_iata, departure_iata, destination_iata, db, archive_end_dt).into(),
-                    EventType::Flight {flight_iata, departure_iata, destination_iata} => transform_flight(calendar_event, flight_iata, departure_iata, destination_iata, db, archive_end_dt).into(),
-                    EventType::Ground {category, description} => transform_ground(calendar_event, category, description, db, archive_end_dt).into(),
-                    EventType::Holiday => transform_holiday(calendar_event, archive_end_dt).into(),
-                    EventType::Layover => transform_layover(calendar_event, db, archive_end_dt).into(),
-                    EventType::Off => transform_off(calendar_event, archive_end_dt).into(),
-                    EventType::Pickup => transform_pickup(calendar_event, db, archive_end_dt).into(),
-                    EventType::Reserve {description} => transform_reserve(calendar_event, description, db, archive_end_dt).into(),
-                    EventType::Sickness => transform_sickness(calendar_event, archive_end_dt).into(),
-                    EventType::Unknown => transform_unknown(calendar_event, archive_end_dt).into(),
-                }
-            },
-            _ => calendar_component, // if not event: forward unchanged
-        }
-    }).collect();
-    let output_calendar: String = regex::Regex::new(ALERT_TRIGGER_PATTERN).expect("Compiling alert trigger regex failed.").replace_all(&output_calendar.to_string(), |captures: &regex::Captures|
+                let t_trigger: i32 = captures["t_trigger"].parse().expect("Parsing alert trigger to i32 failed even though regex should have made sure it can't."); // parse alert trigger
+                if t_trigger.rem_euclid(3600) == 0 {format!("PT{}H", t_trigger / 3600)} // if alert trigger is a multiple of an hour: convert to hours
+                else if t_trigger.rem_euclid(60) == 0 {format!("PT{}M", t_trigger / 60)} // if alert trigger is a multiple of a minute: convert to minutes
+                else {captures["t_trigger"].to_owned()} // return unchanged
+            }).to_string(); // Calendar -> String, convert alert triggers in seconds to hours or minutes for google calendar compatibility
+        },
+        OutputMode::FreeBusy => (output_calendar, event_counts) = generate_freebusy(input_calendar, archive_end_dt, config, event_type_patterns), // only times are output, so transforming would be wasted
+    }
+    let prodid_regex: regex::Regex = regex::Regex::new(PRODID_PATTERN).expect("Compiling PRODID regex failed.");
+    let output_calendar: String = match prodid_regex.is_match(&output_calendar) // set PRODID identifying this program
     {
-        let t_trigger: i32 = captures["t_trigger"].parse().expect("Parsing alert trigger to i32 failed even though regex should have made sure it can't."); // parse alert trigger
-        if t_trigger.rem_euclid(3600) == 0 {format!("PT{}H", t_trigger / 3600)} // if alert trigger is a multiple of an hour: convert to hours
-        else if t_trigger.rem_euclid(60) == 0 {format!("PT{}M", t_trigger / 60)} // if alert trigger is a multiple of a minute: convert to minutes
-        else {captures["t_trigger"].to_owned()} // return unchanged
-    }).to_string(); // Calendar -> String, convert alert triggers in seconds to hours or minutes for google calendar compatibility
+        true => prodid_regex.replace(&output_calendar, regex::NoExpand(format!("PRODID:{PRODID}").as_str())).to_string(), // replace library default
+        false => output_calendar.replacen("VERSION:2.0\r\n", format!("VERSION:2.0\r\nPRODID:{PRODID}\r\n").as_str(), 1), // insert after version
+    };
     log::info!("Transformed calendar.");
     log::debug!("{output_calendar}");
 
 
-    if let Some(parent) = std::path::Path::new(output_calendar_filepath).parent()
+    for output_calendar_filepath in config.OUTPUT_CALENDAR_FILEPATH.iter() // save output calendar to every destination, failing destinations do not stop the others
+    {
+        if let Some(parent) = std::path::Path::new(output_calendar_filepath).parent()
+        {
+            if let Err(e) = std::fs::create_dir_all(parent) // create parent directories if necessary
+            {
+                log::error!("Creating parent directories of \"{output_calendar_filepath}\" failed with: {e}");
+                output_calendar_errors.push(e);
+                continue;
+            }
+        }
+        if let Ok(s) = std::fs::read_to_string(output_calendar_filepath) && is_calendar_unchanged(s.as_str(), output_calendar.as_str()) // if existing calendar is unchanged: skip writing to not trigger downstream watchers needlessly, if reading failed just write
+        {
+            log::info!("Transformed calendar at \"{output_calendar_filepath}\" has no changes. Skipped saving.");
+            continue;
+        }
+        match write_file_atomic(output_calendar_filepath, output_calendar.as_str()) // save output calendar, atomically so calendar is never served half written
+        {
+            Ok(()) => log::info!("Saved transformed calendar to \"{output_calendar_filepath}\"."),
+            Err(e) =>
+            {
+                log::error!("Saving transformed calendar to \"{output_calendar_filepath}\" failed with: {e}");
+                output_calendar_errors.push(e);
+            },
+        }
+    }
+    if output_calendar_errors.len() == config.OUTPUT_CALENDAR_FILEPATH.len() && let Some(e) = output_calendar_errors.pop() // if every destination failed: iteration failed
     {
-        std::fs::create_dir_all(parent)?; // create parent directories if necessary
+        return Err(e.into());
     }
-    std::fs::write(output_calendar_filepath, output_calendar)?; // save output calendar
-    log::info!("Saved transformed calendar to \"{output_calendar_filepath}\".");
+    log::info!("Iteration done: {event_counts}");
+
+    return Ok(event_counts);
+}
+
 
-    return Ok(());
+/// # Summary
+/// Compares 2 calendars in ical format while ignoring DTSTAMP and LAST-MODIFIED, as these are set to the conversion time every iteration.
+///
+/// # Arguments
+/// - `calendar_1`: first calendar
+/// - `calendar_2`: second calendar
+///
+/// # Returns
+/// - true if unchanged, false otherwise
+fn is_calendar_unchanged(calendar_1: &str, calendar_2: &str) -> bool
+{
+    let is_relevant = |line: &&str| !line.starts_with("DTSTAMP:") && !line.starts_with("LAST-MODIFIED:"); // timestamps change every iteration, content does not
+
+
+    return calendar_1.lines().filter(is_relevant).eq(calendar_2.lines().filter(is_relevant));
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::build_http_client::*;
+    use crate::test_harness::*;
+
+
+    /// # Summary
+    /// Runs 1 iteration against a local server serving `input_calendar` and returns the saved output calendar.
+    fn run_update_calendar(input_calendar: String, config: Config) -> String
+    {
+        let db = test_db();
+        let (url, _requests) = serve(vec![(Some("text/calendar"), input_calendar.into_bytes())]);
+        let output_calendar_filepath: std::path::PathBuf = temp_path("duty_plan.ics");
+        let config: Config = Config {INPUT_CALENDAR_URL: url, OUTPUT_CALENDAR_FILEPATH: vec![output_calendar_filepath.to_string_lossy().into_owned()], ..config};
+
+
+        update_calendar(&build_http_client(&config).unwrap(), &config, &db, &archive_end_dt(), &patterns_default()).unwrap();
+        let output_calendar: String = std::fs::read_to_string(&output_calendar_filepath).unwrap();
+        std::fs::remove_file(output_calendar_filepath).unwrap();
+        return output_calendar;
+    }
+
+
+    #[test]
+    fn prodid_is_replaced_once()
+    {
+        let output_calendar: String = run_update_calendar(calendar_minimal(), Config::default());
+
+
+        assert_eq!(output_calendar.lines().filter(|line| line.starts_with("PRODID:")).collect::<Vec<&str>>(), vec![concat!("PRODID:-//9-FS//", env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"), "//EN")]); // library default replaced, not added
+        assert!(!output_calendar.contains("METHOD:")); // omitted if unset
+    }
+
+
+    #[test]
+    fn method_is_set_if_configured()
+    {
+        let output_calendar: String = run_update_calendar(calendar_minimal(), Config {OUTPUT_CALENDAR_METHOD: Some("PUBLISH".to_owned()), ..Config::default()});
+
+
+        assert!(output_calendar.lines().any(|line| line == "METHOD:PUBLISH"));
+    }
+
+
+    #[test]
+    fn every_output_is_written_and_only_all_failing_fails()
+    {
+        let db = test_db();
+        let (url, _requests) = serve(vec![(Some("text/calendar"), calendar_minimal().into_bytes()), (Some("text/calendar"), calendar_minimal().into_bytes())]);
+        let output_calendar_filepaths: [std::path::PathBuf; 2] = [temp_path("duty_plan_1.ics"), temp_path("duty_plan_2.ics")];
+        let not_a_dir: std::path::PathBuf = temp_path("not_a_dir");
+        std::fs::write(&not_a_dir, "").unwrap(); // file as parent directory: creating parent directories fails
+        let output_calendar_filepath_invalid: String = not_a_dir.join("duty_plan.ics").to_string_lossy().into_owned();
+        let config: Config = Config
+        {
+            INPUT_CALENDAR_URL: url,
+            OUTPUT_CALENDAR_FILEPATH: vec![output_calendar_filepaths[0].to_string_lossy().into_owned(), output_calendar_filepath_invalid.clone(), output_calendar_filepaths[1].to_string_lossy().into_owned()],
+            ..Config::default()
+        };
+        let http_client: reqwest::blocking::Client = build_http_client(&config).unwrap();
+
+
+        assert!(update_calendar(&http_client, &config, &db, &archive_end_dt(), &patterns_default()).is_ok()); // 1 failing destination does not fail iteration
+        let output_calendar: String = std::fs::read_to_string(&output_calendar_filepaths[0]).unwrap();
+        assert!(output_calendar.contains("BEGIN:VEVENT"));
+        assert_eq!(std::fs::read_to_string(&output_calendar_filepaths[1]).unwrap(), output_calendar); // every destination gets the same calendar
+
+        let config: Config = Config {OUTPUT_CALENDAR_FILEPATH: vec![output_calendar_filepath_invalid], ..config};
+        assert!(update_calendar(&http_client, &config, &db, &archive_end_dt(), &patterns_default()).is_err()); // every destination failed: iteration failed
+        for filepath in output_calendar_filepaths.iter().chain([&not_a_dir])
+        {
+            std::fs::remove_file(filepath).unwrap();
+        }
+    }
+
+
+    #[test]
+    fn unchanged_calendar_is_not_written()
+    {
+        let db = test_db();
+        let (url, _requests) = serve(vec![(Some("text/calendar"), calendar_minimal().into_bytes()), (Some("text/calendar"), calendar_minimal().into_bytes()), (Some("text/calendar"), calendar_minimal().replace("OFF DAY (X)", "SICKNESS (K)").into_bytes())]);
+        let output_calendar_filepath: std::path::PathBuf = temp_path("duty_plan.ics");
+        let config: Config = Config {INPUT_CALENDAR_URL: url, OUTPUT_CALENDAR_FILEPATH: vec![output_calendar_filepath.to_string_lossy().into_owned()], ..Config::default()};
+        let http_client: reqwest::blocking::Client = build_http_client(&config).unwrap();
+
+
+        update_calendar(&http_client, &config, &db, &archive_end_dt(), &patterns_default()).unwrap();
+        let output_calendar_old: String = regex::Regex::new(r"(?m)^(DTSTAMP|LAST-MODIFIED):.*$").unwrap().replace_all(&std::fs::read_to_string(&output_calendar_filepath).unwrap(), "${1}:20000101T000000Z").to_string(); // only timestamps differ from next iteration
+        assert!(output_calendar_old.contains("DTSTAMP:20000101T000000Z\r\n"));
+        std::fs::write(&output_calendar_filepath, &output_calendar_old).unwrap();
+        let mtime_old: std::time::SystemTime = std::fs::metadata(&output_calendar_filepath).unwrap().modified().unwrap();
+
+        update_calendar(&http_client, &config, &db, &archive_end_dt(), &patterns_default()).unwrap();
+        assert_eq!(std::fs::metadata(&output_calendar_filepath).unwrap().modified().unwrap(), mtime_old); // not touched
+        assert_eq!(std::fs::read_to_string(&output_calendar_filepath).unwrap(), output_calendar_old);
+
+        update_calendar(&http_client, &config, &db, &archive_end_dt(), &patterns_default()).unwrap(); // content changed
+        assert!(std::fs::read_to_string(&output_calendar_filepath).unwrap().contains("SUMMARY:Sickness\r\n"));
+        std::fs::remove_file(output_calendar_filepath).unwrap();
+    }
 }
\ No newline at end of file
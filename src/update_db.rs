@@ -6,6 +6,7 @@ use crate::api_response::*;
 use crate::dateperhapstime_to_string::*;
 use crate::error::*;
 use crate::is_archived::*;
+use crate::restore_events::*;
 
 
 /// # Summary
@@ -15,14 +16,18 @@ use crate::is_archived::*;
 /// - `http_client`: http client
 /// - `airport_data_url`: airport data source URL
 /// - `db`: database connection pool
+/// - `min_parse_success_ratio`: minimum ratio of successfully parsed rows, if below the update is skipped to not overwrite good data with a faulty download
 ///
 /// # Returns
 /// - nothing or error
-pub fn update_airports(http_client: &reqwest::blocking::Client, airport_data_url: &str, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> Result<(), UpdateAirportsError>
+pub fn update_airports(http_client: &reqwest::blocking::Client, airport_data_url: &str, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, min_parse_success_ratio: f64) -> Result<(), UpdateAirportsError>
 {
-    const AIRPORT_QUERY: &str = "INSERT OR REPLACE INTO Airport (id, ident, type, name, latitude_deg, longitude_deg, elevation_ft, continent, iso_country, iso_region, municipality, scheduled_service, gps_code, iata_code, local_code, home_link, wikipedia_link, keywords) VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?);"; // query string for Airport table
+    const AIRPORT_QUERY: &str = "INSERT OR REPLACE INTO Airport (id, ident, type, name, latitude_deg, longitude_deg, elevation_ft, continent, iso_country, iso_region, municipality, scheduled_service, gps_code, iata_code, local_code, home_link, wikipedia_link, keywords) VALUES"; // query string for Airport table, row placeholders are appended per batch
+    const AIRPORT_QUERY_ROW: &str = "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)"; // placeholders for 1 row
+    const BATCH_SIZE: usize = 500; // rows per insert statement, 500 * 18 parameters stays well below sqlite's parameter limit
     let mut airports: Vec<AirportDownloadResponse> = std::vec::Vec::new(); // all airports
     let f: scaler::Formatter = scaler::Formatter::new().set_rounding(scaler::Rounding::Magnitude(0)).set_scaling(scaler::Scaling::None); // formatter for logging
+    let mut rows_total: usize = 0; // number of csv rows, parsed successfully or not
 
 
     let r = http_client.get(airport_data_url).send()?; // download airport data
@@ -31,6 +36,7 @@ pub fn update_airports(http_client: &reqwest::blocking::Client, airport_data_url
 
     for (i, row) in csv::Reader::from_reader(r.text()?.as_bytes()).deserialize::<AirportDownloadResponse>().enumerate() // parse csv
     {
+        rows_total += 1;
         match row // parsed row successfully?
         {
             Ok(o) => airports.push(o.clone()),  // parsed successfully: add airport to list
@@ -43,6 +49,12 @@ pub fn update_airports(http_client: &reqwest::blocking::Client, airport_data_url
         log::warn!("Downloaded data does not contain any airports. Skipping update.");
         return Ok(());
     }
+    let parse_success_ratio: f64 = airports.len() as f64 / rows_total as f64; // ratio of successfully parsed rows
+    if parse_success_ratio < min_parse_success_ratio // too many rows failed, probably upstream format change
+    {
+        log::error!("Only {:.1} % of airport data csv rows could be parsed, which is below the minimum of {:.1} %. Skipping update to keep existing data.", parse_success_ratio * 100.0, min_parse_success_ratio * 100.0);
+        return Ok(());
+    }
 
 
     log::info!("Updating airport database...");
@@ -50,30 +62,36 @@ pub fn update_airports(http_client: &reqwest::blocking::Client, airport_data_url
     let mut db_con = db.get()?; // get connection
     let db_tx = db_con.transaction()?; // start transaction so automatic rollback on error
     {
-        let mut db_stmt = db_tx.prepare(AIRPORT_QUERY)?; // prepare bulk insert
-        for airport in airports
+        for airports_batch in airports.chunks(BATCH_SIZE) // insert multiple rows per statement to reduce round trips
         {
-            rows_affected += db_stmt.execute(rusqlite::params! // bind parameters, count rows affected
-            [
-                airport.id,
-                airport.ident,
-                format!("{:?}", airport.r#type),
-                airport.name,
-                airport.latitude_deg,
-                airport.longitude_deg,
-                airport.elevation_ft,
-                format!("{:?}", airport.continent),
-                airport.iso_country,
-                airport.iso_region,
-                airport.municipality,
-                airport.scheduled_service,
-                airport.gps_code,
-                airport.iata_code,
-                airport.local_code,
-                airport.home_link,
-                airport.wikipedia_link,
-                airport.keywords
-            ])?;
+            let mut db_stmt = db_tx.prepare_cached(format!("{AIRPORT_QUERY} {};", vec![AIRPORT_QUERY_ROW; airports_batch.len()].join(", ")).as_str())?; // prepare batch insert, cached so full batches reuse the same statement
+            let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::with_capacity(airports_batch.len() * 18); // parameters of all rows in batch
+            for airport in airports_batch
+            {
+                let row: [Box<dyn rusqlite::ToSql>; 18] = // parameters of 1 row
+                [
+                    Box::new(airport.id),
+                    Box::new(airport.ident.clone()),
+                    Box::new(format!("{:?}", airport.r#type)),
+                    Box::new(airport.name.clone()),
+                    Box::new(airport.latitude_deg),
+                    Box::new(airport.longitude_deg),
+                    Box::new(airport.elevation_ft),
+                    Box::new(format!("{:?}", airport.continent)),
+                    Box::new(airport.iso_country.clone()),
+                    Box::new(airport.iso_region.clone()),
+                    Box::new(airport.municipality.clone()),
+                    Box::new(airport.scheduled_service),
+                    Box::new(airport.gps_code.clone()),
+                    Box::new(airport.iata_code.clone()),
+                    Box::new(airport.local_code.clone()),
+                    Box::new(airport.home_link.clone()),
+                    Box::new(airport.wikipedia_link.clone()),
+                    Box::new(airport.keywords.clone()),
+                ];
+                params.extend(row);
+            }
+            rows_affected += db_stmt.execute(rusqlite::params_from_iter(params.iter()))?; // bind parameters, count rows affected
         }
     }
     db_tx.commit()?; // commit transaction
@@ -84,20 +102,26 @@ pub fn update_airports(http_client: &reqwest::blocking::Client, airport_data_url
 
 
 /// # Summary
-/// Downloads country data from "ourairports.com/data/countries.csv", parses it, and updates the database table "Country".
+/// Downloads country data from "ourairports.com/data/countries.csv", parses it, and updates the database table "Country". Countries are unique by code, if the data contains a code multiple times the last row wins.
 ///
 /// # Arguments
 /// - `http_client`: http client
 /// - `country_data_url`: country data source URL
 /// - `db`: database connection pool
+/// - `min_parse_success_ratio`: minimum ratio of successfully parsed rows, if below the update is skipped to not overwrite good data with a faulty download
 ///
 /// # Returns
 /// - nothing or error
-pub fn update_countries(http_client: &reqwest::blocking::Client, country_data_url: &str, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> Result<(), UpdateCountriesError>
+pub fn update_countries(http_client: &reqwest::blocking::Client, country_data_url: &str, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, min_parse_success_ratio: f64) -> Result<(), UpdateCountriesError>
 {
-    const COUNTRY_QUERY: &str = "INSERT OR REPLACE INTO Country (id, code, name, continent, wikipedia_link, keywords) VALUES (?, ?, ?, ?, ?, ?);"; // query string for Country table
+    const BATCH_SIZE: usize = 500; // rows per insert statement, 500 * 6 parameters stays well below sqlite's parameter limit
+    const COUNTRY_QUERY: &str = "INSERT OR REPLACE INTO Country (id, code, name, continent, wikipedia_link, keywords) VALUES"; // query string for Country table, row placeholders are appended per batch
+    const COUNTRY_QUERY_ROW: &str = "(?, ?, ?, ?, ?, ?)"; // placeholders for 1 row
     let mut countries: Vec<CountryDownloadResponse> = std::vec::Vec::new(); // all countries
+    let mut countries_index: std::collections::HashMap<String, usize> = std::collections::HashMap::new(); // country code -> index in countries, to deduplicate by code
     let f: scaler::Formatter = scaler::Formatter::new().set_rounding(scaler::Rounding::Magnitude(0)).set_scaling(scaler::Scaling::None); // formatter for logging
+    let mut rows_parsed: usize = 0; // number of csv rows parsed successfully, including duplicate codes
+    let mut rows_total: usize = 0; // number of csv rows, parsed successfully or not
 
 
     let r = http_client.get(country_data_url).send()?; // download country data
@@ -105,9 +129,26 @@ pub fn update_countries(http_client: &reqwest::blocking::Client, country_data_ur
     log::info!("Downloaded country data from \"{country_data_url}\".");
     for (i, row) in csv::Reader::from_reader(r.text()?.as_bytes()).deserialize::<CountryDownloadResponse>().enumerate() // parse csv
     {
+        rows_total += 1;
         match row // parsed row successfully?
         {
-            Ok(o) => countries.push(o.clone()),  // parsed successfully: add country to list
+            Ok(o) => // parsed successfully: add country to list
+            {
+                rows_parsed += 1;
+                match countries_index.get(&o.code)
+                {
+                    Some(i_country) => // code already seen: last wins, joins on code must not be ambiguous
+                    {
+                        log::warn!("Country data contains code \"{}\" multiple times. Keeping csv row {}.", o.code, i+1);
+                        countries[*i_country] = o;
+                    },
+                    None =>
+                    {
+                        countries_index.insert(o.code.clone(), countries.len());
+                        countries.push(o);
+                    },
+                }
+            },
             Err(e) => log::warn!("Parsing country data from csv row {} failed with: {e}", i+1), // parsing failed: log warning
         }
     }
@@ -117,24 +158,36 @@ pub fn update_countries(http_client: &reqwest::blocking::Client, country_data_ur
         log::warn!("Downloaded data does not contain any countries. Skipping update.");
         return Ok(());
     }
+    let parse_success_ratio: f64 = rows_parsed as f64 / rows_total as f64; // ratio of successfully parsed rows, duplicates count as parsed
+    if parse_success_ratio < min_parse_success_ratio // too many rows failed, probably upstream format change
+    {
+        log::error!("Only {:.1} % of country data csv rows could be parsed, which is below the minimum of {:.1} %. Skipping update to keep existing data.", parse_success_ratio * 100.0, min_parse_success_ratio * 100.0);
+        return Ok(());
+    }
 
     log::info!("Updating country database...");
     let mut rows_affected: usize = 0; // number of rows affected
     let mut db_con = db.get()?; // get connection
     let db_tx = db_con.transaction()?; // start transaction so automatic rollback on error
     {
-        let mut db_stmt = db_tx.prepare(COUNTRY_QUERY)?; // prepare bulk insert
-        for country in countries
+        for countries_batch in countries.chunks(BATCH_SIZE) // insert multiple rows per statement to reduce round trips
         {
-            rows_affected += db_stmt.execute( // bind parameters, count rows affected
-            (
-                country.id,
-                country.code,
-                country.name,
-                format!("{:?}", country.continent),
-                country.wikipedia_link,
-                country.keywords
-            ))?;
+            let mut db_stmt = db_tx.prepare_cached(format!("{COUNTRY_QUERY} {};", vec![COUNTRY_QUERY_ROW; countries_batch.len()].join(", ")).as_str())?; // prepare batch insert, cached so full batches reuse the same statement
+            let mut params: Vec<Box<dyn rusqlite::ToSql>> = Vec::with_capacity(countries_batch.len() * 6); // parameters of all rows in batch
+            for country in countries_batch
+            {
+                let row: [Box<dyn rusqlite::ToSql>; 6] = // parameters of 1 row
+                [
+                    Box::new(country.id),
+                    Box::new(country.code.clone()),
+                    Box::new(country.name.clone()),
+                    Box::new(format!("{:?}", country.continent)),
+                    Box::new(country.wikipedia_link.clone()),
+                    Box::new(country.keywords.clone()),
+                ];
+                params.extend(row);
+            }
+            rows_affected += db_stmt.execute(rusqlite::params_from_iter(params.iter()))?; // bind parameters, count rows affected
         }
     }
     db_tx.commit()?; // commit transaction
@@ -145,34 +198,33 @@ pub fn update_countries(http_client: &reqwest::blocking::Client, country_data_ur
 
 
 /// # Summary
-/// Downloads calendar from myTime, parses it, and updates the database table "Event". Events that have ended at `archive_end_dt` or prior are considered archived and remain untouched. Events newer than that are considered active and are deleted from the database and then replaced by the downloaded data. Exception is if event database is still empty, then all downloaded events are inserted.
+/// Updates the database table "Event" with the events of the downloaded calendar. Events that have ended at `archive_end_dt` or prior are considered archived and remain untouched. Events newer than that are considered active and are deleted from the database and then replaced by the downloaded data. Exception is if event database is still empty, then all downloaded events are inserted. Before deleting, active events are backed up in table "Event_backup". If `min_active_events_ratio` is set and fewer than that ratio of the previous active events remain after the update, the update is considered suspicious and the backup is restored. Without it an empty roster is accepted, because it can be legitimate, for example before leave. If `archive_max_age` is set, events that have ended longer ago than that are deleted regardless.
 ///
 /// # Arguments
-/// - `http_client`: http client
-/// - `input_calendar_url`: calendar source URL
+/// - `input_calendar`: downloaded input calendar
 /// - `db`: database connection pool
 /// - `archive_end_dt`: datetime when to archive ends, latest datetime to be considered for archiving
+/// - `archive_max_age`: maximum age of events to keep in database, older events are deleted, if None events are kept forever
+/// - `min_active_events_ratio`: minimum ratio of active events after update compared to before, if below backup is restored, if None never restores
+/// - `input_timezone`: timezone of floating datetimes in input calendar, if None assume utc
 ///
 /// # Returns
 /// - nothing or error
-pub fn update_events(http_client: &reqwest::blocking::Client, input_calendar_url: &str, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>) -> Result<(), UpdateEventsError>
+pub fn update_events(input_calendar: &icalendar::Calendar, db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>, archive_end_dt: &chrono::DateTime<chrono::Utc>, archive_max_age: Option<chrono::Duration>, min_active_events_ratio: Option<f64>, input_timezone: Option<chrono_tz::Tz>) -> Result<(), UpdateEventsError>
 {
-    const EVENT_QUERY: [&str; 3] = // query string for Event table
+    const EVENT_QUERY: [&str; 7] = // query string for Event table
     [
         "SELECT * FROM Event;", // check if table is empty or not
         "DELETE FROM Event WHERE ? < end_dt;", // delete all active events, meaning events newer than end of archive
-        "INSERT OR REPLACE INTO Event (uid, summary, start_dt, end_dt, location, description) VALUES (?, ?, ?, ?, ?, ?);" // insert new events
+        "INSERT OR REPLACE INTO Event (uid, summary, start_dt, end_dt, location, description, rrule) VALUES (?, ?, ?, ?, ?, ?, ?);", // insert new events
+        "DELETE FROM Event WHERE end_dt < ?;", // delete all events older than maximum archive age
+        "DELETE FROM Event_backup;", // clear previous backup
+        "INSERT INTO Event_backup (uid, summary, start_dt, end_dt, location, description, rrule) SELECT uid, summary, start_dt, end_dt, location, description, rrule FROM Event WHERE ? < end_dt;", // back up all active events before deleting them
+        "SELECT COUNT(*) FROM Event WHERE ? < end_dt;", // count active events after update
     ];
     let event_db_empty: bool; // check if event database is empty
+    let mut events_backed_up: usize = 0; // number of active events backed up before deleting
     let f: scaler::Formatter = scaler::Formatter::new().set_rounding(scaler::Rounding::Magnitude(0)).set_scaling(scaler::Scaling::None); // formatter for logging
-    let input_calendar: icalendar::Calendar; // input calendar
-
-
-    let r = http_client.get(input_calendar_url).send()?; // download calendar ics
-    log::debug!("{}", r.status());
-    input_calendar = r.text()?.parse()?; // parse calendar ics
-    log::info!("Downloaded and parsed calendar from \"{input_calendar_url}\"."); // log download
-    log::debug!("{input_calendar}");
 
 
     log::info!("Updating event database...");
@@ -196,6 +248,9 @@ pub fn update_events(http_client: &reqwest::blocking::Client, input_calendar_url
 
         if !event_db_empty // if table not empty: delete all active events before inserting new ones
         {
+            db_tx.execute(EVENT_QUERY[4], ())?; // only keep latest backup
+            events_backed_up = db_tx.execute(EVENT_QUERY[5], (archive_end_dt.to_rfc3339(),))?; // back up active events so they can be restored if the update turns out to be suspicious, must convert to iso8601, see below
+            log::debug!("Backed up all active events. Rows affected: {}", f.format(events_backed_up as f64));
             rows_affected = db_tx.execute(EVENT_QUERY[1], (archive_end_dt.to_rfc3339(),))?; // delete all active events, meaning events newer than archive_end_dt, must convert to iso8601 because it does not contain space and default trait conversion contains space which is apparently not properly escaped in rusqlite
             log::debug!("Deleted all active events from event database. Rows affected: {}", f.format(rows_affected as f64));
         }
@@ -224,7 +279,7 @@ pub fn update_events(http_client: &reqwest::blocking::Client, input_calendar_url
             {
                 Some(dt) =>
                 {
-                    match dateperhapstime_to_string(dt) // convert to string
+                    match dateperhapstime_to_string(dt, input_timezone) // convert to string, floating datetimes in input timezone
                     {
                         Ok(dt) => start_str = dt,
                         Err(e) => // if invalid datetime: discard
@@ -244,7 +299,7 @@ pub fn update_events(http_client: &reqwest::blocking::Client, input_calendar_url
             {
                 Some(dt) =>
                 {
-                    match dateperhapstime_to_string(dt) // convert to string
+                    match dateperhapstime_to_string(dt, input_timezone) // convert to string, floating datetimes in input timezone
                     {
                         Ok(dt) => end_str = dt,
                         Err(e) => // if invalid datetime: discard
@@ -275,6 +330,7 @@ pub fn update_events(http_client: &reqwest::blocking::Client, input_calendar_url
                 end_str,
                 location: event.get_location().map(|s| s.to_owned()),
                 description: event.get_description().map(|s| s.to_owned()),
+                rrule: event.property_value("RRULE").map(|s| s.to_owned()), // keep recurrence, otherwise recurring events are flattened to their first occurrence
             });
         }
 
@@ -287,9 +343,28 @@ pub fn update_events(http_client: &reqwest::blocking::Client, input_calendar_url
                 event_to_insert.start_str,
                 event_to_insert.end_str,
                 event_to_insert.location,
-                event_to_insert.description
+                event_to_insert.description,
+                event_to_insert.rrule
             ))?;
         }
+
+        if let Some(min_active_events_ratio) = min_active_events_ratio && 0 < events_backed_up // if restoring enabled and there were active events before: check if update is suspicious
+        {
+            let events_active: usize = db_tx.query_one(EVENT_QUERY[6], (archive_end_dt.to_rfc3339(),), |row| row.get(0))?; // active events after update
+            if (events_active as f64) < (events_backed_up as f64) * min_active_events_ratio // too few active events compared to before: downloaded calendar is likely empty or garbled
+            {
+                log::warn!("Only {} active events remain after update, compared to {} before. Downloaded calendar is likely empty or garbled. Restoring backed up events.", f.format(events_active as f64), f.format(events_backed_up as f64));
+                rows_affected = restore_events(&db_tx, archive_end_dt)?;
+                log::info!("Restored backed up events. Rows affected: {}", f.format(rows_affected as f64));
+            }
+        }
+
+        if let Some(archive_max_age) = archive_max_age // if maximum archive age set: delete events that are too old, after inserting so even initially inserted events are purged
+        {
+            let purged: usize = db_tx.execute(EVENT_QUERY[3], ((chrono::Utc::now() - archive_max_age).to_rfc3339(),))?; // must convert to iso8601, see above
+            log::debug!("Deleted all events older than maximum archive age from event database. Rows affected: {}", f.format(purged as f64));
+            rows_affected += purged;
+        }
     }
     db_tx.commit()?; // commit transaction
     log::info!("Updated event database. Rows affected: {}", f.format(rows_affected as f64));
@@ -307,4 +382,247 @@ pub struct EventRow
     pub end_str: String,
     pub location: Option<String>,
     pub description: Option<String>,
+    pub rrule: Option<String>,
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::airport_resolver::*;
+    use crate::build_http_client::*;
+    use crate::config::*;
+    use crate::test_harness::*;
+
+
+    /// # Summary
+    /// Returns the uids of all events in the database, sorted.
+    fn event_uids(db: &r2d2::Pool<r2d2_sqlite::SqliteConnectionManager>) -> Vec<String>
+    {
+        let db_con = db.get().unwrap();
+        let mut db_stmt = db_con.prepare("SELECT uid FROM Event ORDER BY uid;").unwrap();
+        return db_stmt.query_map((), |row| row.get(0)).unwrap().map(|uid| uid.unwrap()).collect();
+    }
+
+
+    /// # Summary
+    /// Creates a calendar from (uid, start, end) triples.
+    fn calendar(events: &[(&str, &str, &str)]) -> icalendar::Calendar
+    {
+        let mut calendar: icalendar::Calendar = icalendar::Calendar::new();
+        for (uid, start_dt, end_dt) in events
+        {
+            calendar.push(test_event("OFF DAY (X)", start_dt, end_dt, "FRA").uid(uid).done());
+        }
+        return calendar;
+    }
+
+
+    #[test]
+    fn archive_max_age_purges_old_events()
+    {
+        let db = test_db();
+        let archive_end_dt: chrono::DateTime<chrono::Utc> = chrono::Utc::now() - chrono::Duration::weeks(1);
+
+
+        update_events(&calendar(&[("old", "2000-01-01T10:00:00Z", "2000-01-01T11:00:00Z"), ("recent", "2099-01-01T10:00:00Z", "2099-01-01T11:00:00Z")]), &db, &archive_end_dt, Some(chrono::Duration::days(365)), None, None).unwrap();
+
+        assert_eq!(event_uids(&db), vec!["recent".to_owned()]); // purged right after initial insert
+    }
+
+
+    #[test]
+    fn archive_max_age_unset_keeps_old_events()
+    {
+        let db = test_db();
+        let archive_end_dt: chrono::DateTime<chrono::Utc> = chrono::Utc::now() - chrono::Duration::weeks(1);
+
+
+        update_events(&calendar(&[("old", "2000-01-01T10:00:00Z", "2000-01-01T11:00:00Z"), ("recent", "2099-01-01T10:00:00Z", "2099-01-01T11:00:00Z")]), &db, &archive_end_dt, None, None, None).unwrap();
+
+        assert_eq!(event_uids(&db), vec!["old".to_owned(), "recent".to_owned()]);
+    }
+
+
+    /// # Summary
+    /// Creates airport data in csv format like ourairports.com with `n` airports, plus `n_invalid` rows that fail to parse.
+    fn airports_csv(n: usize, n_invalid: usize) -> String
+    {
+        let mut s: String = "id,ident,type,name,latitude_deg,longitude_deg,elevation_ft,continent,iso_country,iso_region,municipality,scheduled_service,gps_code,iata_code,local_code,home_link,wikipedia_link,keywords\n".to_owned();
+        for i in 0..n {s.push_str(format!("{i},X{i:03},small_airport,Airport {i},0,0,,EU,DE,DE-XX,City,no,,,,,,\n").as_str());}
+        for i in n..n + n_invalid {s.push_str(format!("{i},X{i:03},not_a_type,Airport {i},0,0,,EU,DE,DE-XX,City,no,,,,,,\n").as_str());} // invalid airport type
+        return s;
+    }
+
+
+    #[test]
+    fn airports_are_inserted_in_multiple_batches()
+    {
+        let db = test_db();
+        let (url, _requests) = serve(vec![(Some("text/csv"), airports_csv(1201, 0).into_bytes())]); // 2 full batches of 500 and 1 partial batch
+
+
+        update_airports(&build_http_client(&Config::default()).unwrap(), url.as_str(), &db, 0.9).unwrap();
+
+        assert_eq!(count_rows(&db, "Airport"), 1201);
+        assert_eq!(db.get().unwrap().query_one("SELECT name FROM Airport WHERE id = 1200;", (), |row| row.get::<usize, String>(0)).unwrap(), "Airport 1200"); // last row of partial batch bound correctly
+    }
+
+
+    #[test]
+    fn min_parse_success_ratio_threshold()
+    {
+        for (min_parse_success_ratio, rows_expected) in [(0.89, 9), (0.9, 9), (0.91, 0)] // 9 of 10 rows parse, ratio 0.9
+        {
+            let db = test_db();
+            let (url, _requests) = serve(vec![(Some("text/csv"), airports_csv(9, 1).into_bytes())]);
+
+
+            update_airports(&build_http_client(&Config::default()).unwrap(), url.as_str(), &db, min_parse_success_ratio).unwrap(); // skipping is no error
+
+            assert_eq!(count_rows(&db, "Airport"), rows_expected, "MIN_PARSE_SUCCESS_RATIO {min_parse_success_ratio}");
+        }
+    }
+
+
+    #[test]
+    fn suspicious_update_restores_backup()
+    {
+        let archive_end_dt: chrono::DateTime<chrono::Utc> = chrono::Utc::now() - chrono::Duration::weeks(1);
+        let events_full: [(&str, &str, &str); 4] =
+        [
+            ("a", "2099-01-01T10:00:00Z", "2099-01-01T11:00:00Z"),
+            ("b", "2099-01-02T10:00:00Z", "2099-01-02T11:00:00Z"),
+            ("c", "2099-01-03T10:00:00Z", "2099-01-03T11:00:00Z"),
+            ("d", "2099-01-04T10:00:00Z", "2099-01-04T11:00:00Z"),
+        ];
+        let uids_full: Vec<String> = vec!["a".to_owned(), "b".to_owned(), "c".to_owned(), "d".to_owned()];
+
+
+        for (events, min_active_events_ratio, uids_expected) in
+        [
+            (&events_full[..0], Some(0.5), uids_full.clone()), // empty: restored
+            (&events_full[..1], Some(0.5), uids_full.clone()), // shrunken below ratio: restored
+            (&events_full[..3], Some(0.5), vec!["a".to_owned(), "b".to_owned(), "c".to_owned()]), // shrunken within ratio: accepted
+            (&events_full[..1], None, vec!["a".to_owned()]), // ratio unset: never restored
+            (&events_full[..0], None, Vec::new()),
+        ]
+        {
+            let db = test_db();
+            update_events(&calendar(&events_full), &db, &archive_end_dt, None, None, None).unwrap(); // initial insert
+
+
+            update_events(&calendar(events), &db, &archive_end_dt, None, min_active_events_ratio, None).unwrap();
+
+            assert_eq!(event_uids(&db), uids_expected, "{} events, MIN_ACTIVE_EVENTS_RATIO {min_active_events_ratio:?}", events.len());
+        }
+    }
+
+
+    #[test]
+    fn rrule_round_trip()
+    {
+        let db = test_db();
+        let mut input_calendar: icalendar::Calendar = icalendar::Calendar::new();
+        input_calendar.push(test_event("GENERALEVENT (MEETING)", "2099-01-01T10:00:00Z", "2099-01-01T11:00:00Z", "FRA").uid("recurring").add_property("RRULE", "FREQ=WEEKLY;COUNT=4").done());
+
+
+        update_events(&input_calendar, &db, &archive_end_dt(), None, None, None).unwrap();
+        let (output_calendar, _) = run_pipeline(&db, &Config::default()); // load from database and transform
+
+        assert_eq!(property_values(&event_lines(&output_calendar, "recurring"), "RRULE"), vec!["FREQ=WEEKLY;COUNT=4".to_owned()]); // not flattened to first occurrence
+    }
+
+
+    #[test]
+    fn duplicate_country_codes_last_wins()
+    {
+        let db = test_db();
+        let (url, _requests) = serve(vec![(Some("text/csv"), "id,code,name,continent,wikipedia_link,keywords\n1,DE,Germany (old),EU,,\n2,FR,France,EU,,\n3,DE,Germany,EU,,\n".as_bytes().to_vec())]);
+
+
+        update_countries(&build_http_client(&Config::default()).unwrap(), url.as_str(), &db, 0.9).unwrap(); // duplicates count as parsed, so ratio is not affected
+
+        assert_eq!(count_rows(&db, "Country"), 2);
+        assert_eq!(db.get().unwrap().query_one("SELECT id, name FROM Country WHERE code = 'DE';", (), |row| Ok((row.get::<usize, u32>(0)?, row.get::<usize, String>(1)?))).unwrap(), (3, "Germany".to_owned())); // last row wins completely
+    }
+
+
+    #[test]
+    fn refreshed_airports_are_resolved()
+    {
+        const HEADER: &str = "id,ident,type,name,latitude_deg,longitude_deg,elevation_ft,continent,iso_country,iso_region,municipality,scheduled_service,gps_code,iata_code,local_code,home_link,wikipedia_link,keywords\n";
+        let db = test_db();
+        seed_country(&db, 1, "DE", "Germany");
+        let (url, _requests) = serve(vec!
+        [
+            (Some("text/csv"), format!("{HEADER}1,EDDF,large_airport,Rhein-Main Airport,50,8,,EU,DE,DE-HE,Frankfurt,yes,EDDF,FRA,FRA,,,\n").into_bytes()),
+            (Some("text/csv"), format!("{HEADER}1,EDDF,large_airport,Frankfurt am Main Airport,50,8,,EU,DE,DE-HE,Frankfurt am Main,yes,EDDF,FRA,FRA,,,\n2,EDDM,large_airport,Munich Airport,48,11,,EU,DE,DE-BY,Munich,yes,EDDM,MUC,MUC,,,\n").into_bytes()), // renamed and added
+        ]);
+        let http_client: reqwest::blocking::Client = build_http_client(&Config::default()).unwrap();
+
+
+        update_airports(&http_client, url.as_str(), &db, 0.9).unwrap();
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode);
+        assert_eq!(airport_resolver.lookup_iata("FRA").map(|row| row.airport_name), Some("Rhein-Main Airport".to_owned()));
+        assert_eq!(airport_resolver.try_iata_to_icao("MUC"), "MUC"); // not known yet
+
+        update_airports(&http_client, url.as_str(), &db, 0.9).unwrap(); // refresh
+        let airport_resolver: AirportResolver = AirportResolver::new(&db, IcaoSourceColumn::GpsCode); // new conversion, new cache
+        assert_eq!(count_rows(&db, "Airport"), 2);
+        assert_eq!(airport_resolver.lookup_iata("FRA").map(|row| (row.airport_name, row.airport_municipality)), Some(("Frankfurt am Main Airport".to_owned(), "Frankfurt am Main".to_owned()))); // replaced, not duplicated
+        assert_eq!(airport_resolver.try_iata_to_icao("MUC"), "EDDM");
+    }
+
+
+    #[test]
+    #[ignore = "benchmark, run with `cargo test --release -- --ignored --nocapture`"]
+    fn batched_inserts_are_faster_than_single_rows()
+    {
+        const AIRPORT_QUERY: &str = "INSERT OR REPLACE INTO Airport (id, ident, type, name, latitude_deg, longitude_deg, elevation_ft, continent, iso_country, iso_region, municipality, scheduled_service, gps_code, iata_code, local_code, home_link, wikipedia_link, keywords) VALUES"; // same as update_airports
+        const AIRPORT_QUERY_ROW: &str = "(?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)";
+        const ROWS: usize = 50000; // roughly size of ourairports.com data
+        let row = |i: usize| -> [Box<dyn rusqlite::ToSql>; 18] {[Box::new(i), Box::new(format!("X{i:05}")), Box::new("small_airport"), Box::new(format!("Airport {i}")), Box::new(0.0), Box::new(0.0), Box::new(None::<i32>), Box::new("EU"), Box::new("DE"), Box::new("DE-XX"), Box::new("City"), Box::new(false), Box::new(None::<String>), Box::new(None::<String>), Box::new(None::<String>), Box::new(None::<String>), Box::new(None::<String>), Box::new(None::<String>)]};
+        let insert = |batch_size: usize| -> std::time::Duration // insert all rows in 1 transaction, `batch_size` rows per statement
+        {
+            let db = test_db();
+            let mut db_con = db.get().unwrap();
+            let start: std::time::Instant = std::time::Instant::now();
+            let db_tx = db_con.transaction().unwrap();
+            for batch in (0..ROWS).collect::<Vec<usize>>().chunks(batch_size)
+            {
+                let mut db_stmt = db_tx.prepare_cached(format!("{AIRPORT_QUERY} {};", vec![AIRPORT_QUERY_ROW; batch.len()].join(", ")).as_str()).unwrap();
+                db_stmt.execute(rusqlite::params_from_iter(batch.iter().flat_map(|i| row(*i)))).unwrap();
+            }
+            db_tx.commit().unwrap();
+            let duration: std::time::Duration = start.elapsed();
+            assert_eq!(count_rows(&db, "Airport"), ROWS);
+            return duration;
+        };
+
+
+        let duration_single: std::time::Duration = insert(1);
+        let duration_batched: std::time::Duration = insert(500); // same as update_airports
+
+        println!("{ROWS} airports: 1 row per statement {duration_single:?}, 500 rows per statement {duration_batched:?}, {:.1}x faster", duration_single.as_secs_f64() / duration_batched.as_secs_f64());
+        assert!(duration_batched < duration_single);
+    }
+
+
+    #[test]
+    fn empty_roster_stays_empty_by_default()
+    {
+        let archive_end_dt: chrono::DateTime<chrono::Utc> = chrono::Utc::now() - chrono::Duration::weeks(1);
+        let db = test_db();
+        update_events(&calendar(&[("a", "2099-01-01T10:00:00Z", "2099-01-01T11:00:00Z")]), &db, &archive_end_dt, None, None, None).unwrap(); // initial insert
+
+
+        for _ in 0..2 // not brought back in any following iteration
+        {
+            update_events(&calendar(&[]), &db, &archive_end_dt, None, None, None).unwrap(); // roster really became empty, for example before leave
+
+            assert_eq!(event_uids(&db), Vec::<String>::new());
+        }
+    }
 }
\ No newline at end of file
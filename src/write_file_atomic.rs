@@ -0,0 +1,94 @@
+// Copyright (c) 2025 구FS, all rights reserved. Subject to the MIT licence in `licence.md`.
+
+
+/// # Summary
+/// Writes `content` to `filepath` atomically. Writes to a temporary file in the same directory first and then renames it into place, so readers either see the old or the new complete content, never a partially written file. The temporary file is removed on error.
+///
+/// # Arguments
+/// - `filepath`: file path to write to
+/// - `content`: content to write
+///
+/// # Returns
+/// - nothing or error
+pub fn write_file_atomic(filepath: &str, content: &str) -> Result<(), std::io::Error>
+{
+    static COUNTER: std::sync::atomic::AtomicUsize = std::sync::atomic::AtomicUsize::new(0); // distinguishes calls within this process
+    let filepath: &std::path::Path = std::path::Path::new(filepath);
+    let filepath_tmp: std::path::PathBuf; // temporary file path, same directory so rename does not cross filesystems
+
+
+    filepath_tmp = filepath.with_file_name(format!(".{}.{}.{}.tmp", filepath.file_name().unwrap_or_default().to_string_lossy(), std::process::id(), COUNTER.fetch_add(1, std::sync::atomic::Ordering::Relaxed))); // hidden, with process id and counter to not collide with other instances or a timed out iteration still writing
+
+    if let Err(e) = std::fs::write(&filepath_tmp, content) // write temporary file
+    {
+        let _ = std::fs::remove_file(&filepath_tmp); // clean up, ignore error because file might not exist
+        return Err(e);
+    }
+    if let Err(e) = std::fs::rename(&filepath_tmp, filepath) // replace destination atomically
+    {
+        let _ = std::fs::remove_file(&filepath_tmp); // clean up, ignore error because file might not exist
+        return Err(e);
+    }
+
+    return Ok(());
+}
+
+
+#[cfg(test)]
+mod tests
+{
+    use super::*;
+    use crate::test_harness::*;
+
+
+    #[test]
+    fn content_is_replaced_without_leftovers()
+    {
+        let dirpath: std::path::PathBuf = temp_path("write_file_atomic");
+        let filepath: std::path::PathBuf = dirpath.join("duty_plan.ics");
+        std::fs::create_dir_all(&dirpath).unwrap();
+
+
+        write_file_atomic(filepath.to_str().unwrap(), "old").unwrap();
+        write_file_atomic(filepath.to_str().unwrap(), "new").unwrap(); // replace existing file
+
+        assert_eq!(std::fs::read_to_string(&filepath).unwrap(), "new");
+        assert_eq!(std::fs::read_dir(&dirpath).unwrap().count(), 1); // temporary file renamed, not left behind
+        std::fs::remove_dir_all(dirpath).unwrap();
+    }
+
+
+    #[test]
+    fn missing_directory_fails()
+    {
+        let filepath: std::path::PathBuf = temp_path("does_not_exist").join("duty_plan.ics");
+
+
+        assert!(write_file_atomic(filepath.to_str().unwrap(), "content").is_err());
+        assert!(!std::fs::exists(&filepath).unwrap());
+    }
+
+
+    #[test]
+    fn concurrent_writes_do_not_collide()
+    {
+        let dirpath: std::path::PathBuf = temp_path("write_file_atomic_concurrent");
+        let filepath: std::path::PathBuf = dirpath.join("duty_plan.ics");
+        std::fs::create_dir_all(&dirpath).unwrap();
+
+
+        let handles: Vec<std::thread::JoinHandle<Vec<Result<(), std::io::Error>>>> = (0..8).map(|i| // same process, so process id alone would give every thread the same temporary file
+        {
+            let filepath: String = filepath.to_string_lossy().into_owned();
+            std::thread::spawn(move || (0..20).map(|_| write_file_atomic(filepath.as_str(), format!("content {i}").as_str())).collect())
+        }).collect();
+        for handle in handles
+        {
+            assert!(handle.join().unwrap().iter().all(|result| result.is_ok()));
+        }
+
+        assert!(std::fs::read_to_string(&filepath).unwrap().starts_with("content ")); // complete content of 1 writer
+        assert_eq!(std::fs::read_dir(&dirpath).unwrap().count(), 1); // no temporary files left behind
+        std::fs::remove_dir_all(dirpath).unwrap();
+    }
+}
\ No newline at end of file